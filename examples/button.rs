@@ -4,13 +4,17 @@ extern crate webrender;
 extern crate rand;
 
 use weld::application::Application;
+use weld::transition::{Transition, TransitionProperty};
+use weld::window::{WindowOptions, WindowProperties};
 use weld::model::*;
-use weld::window::Interaction;
+use weld::gesture::Gesture;
+use weld::input::MouseButton;
 use weld::layout::{FlexDirection, Percent, Point, Wrap};
 use weld::layout::FlexStyle::*;
 use weld::layout::Align::*;
 use webrender::api::*;
 use rand::{random, Closed01};
+use std::time::Duration;
 
 #[derive(Debug)]
 struct Container {}
@@ -72,28 +76,28 @@ impl State for MyAppState {
                         Height(32.point()),
                     ])
                     .name("button")
+                    .transition(Transition::new(TransitionProperty::Width, Duration::from_millis(150)))
+                    .transition(Transition::new(TransitionProperty::Color, Duration::from_millis(300)))
                     .on(Box::new(|state: Self, event| {
                         match *event {
-                            Interaction::Pressed => {
-                                println!("pressed!");
-                                Ok(Self {
-                                    button_width: state.button_width + 5,
-                                    ..state
-                                })
-                            }
-                            Interaction::Released => {
-                                println!("released!");
+                            Gesture::Click(ref e) if e.button == Some(MouseButton::Left) => {
+                                println!("clicked at {:?}!", e.local_position);
 
                                 let button_color = ColorF::new(random::<Closed01<f32>>().0, random::<Closed01<f32>>().0, random::<Closed01<f32>>().0, 1.0);
                                 Ok(Self {
+                                    button_width: state.button_width + 5,
                                     button_color,
-                                    ..state
                                 })
                             }
+                            _ => Err(InvocationError)
                         }
                     }))
             )
     }
+
+    fn window(&self) -> WindowProperties {
+        WindowProperties::new().title(format!("Demo - button is {}px wide", self.button_width))
+    }
 }
 
 fn main() {
@@ -102,7 +106,7 @@ fn main() {
     let app = Application::new("Demo", MyAppState {
         button_width: 100,
         button_color: ColorF::new(0.0, 0.0, 1.0, 1.0),
-    });
+    }).window(WindowOptions::default().size(800, 600).min_size(320, 240));
 
     app.run();
 }
\ No newline at end of file
@@ -0,0 +1,323 @@
+use webrender::api::{ColorF, LayoutPoint, LayoutSize};
+use std::time::Duration;
+
+/// Sent to the root component of every window on each frame while `State::animating()` returns
+/// true, with the time that passed since the previous frame.
+#[derive(Clone, Debug)]
+pub struct AnimationFrame {
+    pub elapsed: Duration,
+}
+
+impl_describe_debug!(AnimationFrame);
+
+/// Something that changes over time and is advanced on every `AnimationFrame`.
+pub trait Animation {
+    fn advance(&mut self, elapsed: Duration);
+
+    /// Whether advancing the animation further would still change its value.
+    fn is_active(&self) -> bool;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Easing {
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+    /// A cubic bezier curve from (0, 0) to (1, 1) with the two given control points, like the CSS
+    /// `cubic-bezier()` timing function.
+    CubicBezier(f32, f32, f32, f32),
+}
+
+impl Default for Easing {
+    fn default() -> Easing {
+        Easing::Linear
+    }
+}
+
+impl Easing {
+    /// Maps the progress `t` of an animation, between 0 and 1, to the progress of its value.
+    pub fn apply(&self, t: f32) -> f32 {
+        let t = t.max(0.0).min(1.0);
+        match *self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t * t,
+            Easing::EaseOut => 1.0 - (1.0 - t).powi(3),
+            Easing::EaseInOut => {
+                if t < 0.5 {
+                    4.0 * t * t * t
+                } else {
+                    1.0 - (-2.0 * t + 2.0).powi(3) / 2.0
+                }
+            },
+            Easing::CubicBezier(x1, y1, x2, y2) => {
+                let s = solve_bezier_x(t, x1, x2);
+                bezier(s, y1, y2)
+            }
+        }
+    }
+}
+
+fn bezier(s: f32, p1: f32, p2: f32) -> f32 {
+    let inv = 1.0 - s;
+    3.0 * inv * inv * s * p1 + 3.0 * inv * s * s * p2 + s * s * s
+}
+
+fn bezier_slope(s: f32, p1: f32, p2: f32) -> f32 {
+    let inv = 1.0 - s;
+    3.0 * inv * inv * p1 + 6.0 * inv * s * (p2 - p1) + 3.0 * s * s * (1.0 - p2)
+}
+
+/// Finds the curve parameter at which the x coordinate equals `x`.
+fn solve_bezier_x(x: f32, x1: f32, x2: f32) -> f32 {
+    // Newton's method converges quickly for most curves...
+    let mut s = x;
+    for _ in 0..8 {
+        let error = bezier(s, x1, x2) - x;
+        if error.abs() < 1e-6 {
+            return s;
+        }
+        let slope = bezier_slope(s, x1, x2);
+        if slope.abs() < 1e-6 {
+            break;
+        }
+        s -= error / slope;
+    }
+
+    // ...and bisection handles the ones where the slope gets too flat
+    let (mut low, mut high) = (0.0, 1.0);
+    s = x;
+    for _ in 0..32 {
+        let value = bezier(s, x1, x2);
+        if (value - x).abs() < 1e-6 {
+            break;
+        }
+        if value < x {
+            low = s;
+        } else {
+            high = s;
+        }
+        s = (low + high) / 2.0;
+    }
+    s
+}
+
+/// Values that can be animated by a `Timeline`.
+pub trait Interpolate {
+    /// Returns the value at `t` between `self` (0) and `to` (1). `t` can go outside of that range
+    /// for easing curves that overshoot.
+    fn interpolate(&self, to: &Self, t: f32) -> Self;
+}
+
+impl Interpolate for f32 {
+    fn interpolate(&self, to: &f32, t: f32) -> f32 {
+        self + (to - self) * t
+    }
+}
+
+impl Interpolate for LayoutPoint {
+    fn interpolate(&self, to: &LayoutPoint, t: f32) -> LayoutPoint {
+        LayoutPoint::new(self.x.interpolate(&to.x, t), self.y.interpolate(&to.y, t))
+    }
+}
+
+impl Interpolate for LayoutSize {
+    fn interpolate(&self, to: &LayoutSize, t: f32) -> LayoutSize {
+        LayoutSize::new(self.width.interpolate(&to.width, t), self.height.interpolate(&to.height, t))
+    }
+}
+
+impl Interpolate for ColorF {
+    fn interpolate(&self, to: &ColorF, t: f32) -> ColorF {
+        ColorF::new(self.r.interpolate(&to.r, t), self.g.interpolate(&to.g, t), self.b.interpolate(&to.b, t), self.a.interpolate(&to.a, t))
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Keyframe<T> {
+    to: T,
+    duration: Duration,
+    easing: Easing,
+}
+
+/// A value that moves through a sequence of keyframes, each reached after its own duration and
+/// with its own easing curve.
+#[derive(Clone, Debug)]
+pub struct Timeline<T> {
+    from: T,
+    keyframes: Vec<Keyframe<T>>,
+    elapsed: Duration,
+    repeat: bool,
+}
+
+impl<T: Interpolate + Clone> Timeline<T> {
+    pub fn new(from: T) -> Timeline<T> {
+        Timeline {
+            from,
+            keyframes: Vec::new(),
+            elapsed: Duration::from_millis(0),
+            repeat: false,
+        }
+    }
+
+    /// A timeline that animates from one value to another.
+    pub fn tween(from: T, to: T, duration: Duration, easing: Easing) -> Timeline<T> {
+        Timeline::new(from).then(to, duration, easing)
+    }
+
+    /// Adds a keyframe that is reached `duration` after the previous one.
+    pub fn then(mut self, to: T, duration: Duration, easing: Easing) -> Self {
+        self.keyframes.push(Keyframe { to, duration, easing });
+        self
+    }
+
+    /// Holds the current end value for `duration`.
+    pub fn delay(self, duration: Duration) -> Self {
+        let to = self.end_value();
+        self.then(to, duration, Easing::Linear)
+    }
+
+    /// Starts over from the beginning whenever the end is reached.
+    pub fn repeat(mut self, repeat: bool) -> Self {
+        self.repeat = repeat;
+        self
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.keyframes.iter().fold(Duration::from_millis(0), |total, keyframe| total + keyframe.duration)
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    pub fn value(&self) -> T {
+        let mut start = self.from.clone();
+        let mut remaining = self.elapsed;
+
+        for keyframe in &self.keyframes {
+            if remaining < keyframe.duration {
+                let t = seconds(remaining) / seconds(keyframe.duration);
+                return start.interpolate(&keyframe.to, keyframe.easing.apply(t));
+            }
+            remaining -= keyframe.duration;
+            start = keyframe.to.clone();
+        }
+
+        start
+    }
+
+    fn end_value(&self) -> T {
+        self.keyframes.last().map_or_else(|| self.from.clone(), |keyframe| keyframe.to.clone())
+    }
+}
+
+impl<T: Interpolate + Clone> Animation for Timeline<T> {
+    fn advance(&mut self, elapsed: Duration) {
+        let duration = self.duration();
+        self.elapsed += elapsed;
+
+        if self.elapsed >= duration {
+            if self.repeat && duration > Duration::from_millis(0) {
+                let nanos = duration_nanos(self.elapsed) % duration_nanos(duration);
+                self.elapsed = Duration::new((nanos / 1_000_000_000) as u64, (nanos % 1_000_000_000) as u32);
+            } else {
+                self.elapsed = duration;
+            }
+        }
+    }
+
+    fn is_active(&self) -> bool {
+        self.repeat || self.elapsed < self.duration()
+    }
+}
+
+/// A value that follows its target like a damped spring, which keeps its velocity when the target
+/// changes halfway.
+#[derive(Clone, Debug)]
+pub struct Spring {
+    pub value: f32,
+    pub velocity: f32,
+    pub target: f32,
+    pub stiffness: f32,
+    pub damping: f32,
+    pub mass: f32,
+    /// The spring comes to rest once both its distance to the target and its velocity are below
+    /// this threshold.
+    pub precision: f32,
+    /// Time advanced by that didn't make up a whole integration step yet.
+    leftover: Duration,
+}
+
+impl Spring {
+    pub fn new(value: f32) -> Spring {
+        Spring {
+            value,
+            velocity: 0.0,
+            target: value,
+            stiffness: 170.0,
+            damping: 26.0,
+            mass: 1.0,
+            precision: 0.01,
+            leftover: Duration::from_secs(0),
+        }
+    }
+
+    pub fn stiffness(mut self, stiffness: f32) -> Self {
+        self.stiffness = stiffness;
+        self
+    }
+
+    pub fn damping(mut self, damping: f32) -> Self {
+        self.damping = damping;
+        self
+    }
+
+    pub fn mass(mut self, mass: f32) -> Self {
+        self.mass = mass;
+        self
+    }
+
+    pub fn set_target(&mut self, target: f32) {
+        self.target = target;
+    }
+}
+
+impl Animation for Spring {
+    fn advance(&mut self, elapsed: Duration) {
+        // Integrate in fixed steps, so that the outcome doesn't depend on the frame rate. Time that
+        // is shorter than a step is carried over to the next frame.
+        const STEP: f32 = 0.001;
+        const STEP_NANOS: u64 = 1_000_000;
+
+        if !self.is_active() {
+            return;
+        }
+
+        let pending = duration_nanos(self.leftover + elapsed);
+        self.leftover = Duration::from_nanos(pending % STEP_NANOS);
+        for _ in 0..pending / STEP_NANOS {
+            let force = -self.stiffness * (self.value - self.target) - self.damping * self.velocity;
+            self.velocity += force / self.mass * STEP;
+            self.value += self.velocity * STEP;
+        }
+
+        if !self.is_active() {
+            self.value = self.target;
+            self.velocity = 0.0;
+            self.leftover = Duration::from_secs(0);
+        }
+    }
+
+    fn is_active(&self) -> bool {
+        (self.value - self.target).abs() > self.precision || self.velocity.abs() > self.precision
+    }
+}
+
+pub(crate) fn seconds(duration: Duration) -> f32 {
+    duration.as_secs() as f32 + duration.subsec_nanos() as f32 / 1_000_000_000.0
+}
+
+fn duration_nanos(duration: Duration) -> u64 {
+    duration.as_secs() * 1_000_000_000 + duration.subsec_nanos() as u64
+}
@@ -1,93 +1,1443 @@
-use model::{Component, InvocationError, State};
-use window::{WindowEvent, WebrenderWindow, RendererHandle};
+use animation::AnimationFrame;
+use model::{Component, ComponentPath, Event, InvocationError, State};
+use window::{ChildWindow, ChildWindowClosed, EventStream, RendererHandle, Screenshot, WebrenderWindow, WindowEvent, WindowId, WindowOptions, WindowProperties, WindowPropertyChanges, WindowResized, window_property_changes};
 use layout_context::LayoutContext;
+use input::{Interaction, Modifiers, MouseButton, TouchEvent, TouchPhase};
+use gesture::{GestureConfig, GestureRecognizer, Gesture};
+use history::{Cause, ComponentInfo, History};
+use inspector;
+use middleware::{Middleware, StateChange};
+use persistence::{Persistence, WindowGeometry};
+use recording::{RecordedInput, Recorder};
+use remote::RemoteServer;
+use subscription::{self, Source};
+use transition::Transitions;
+use undo::UndoStack;
+use webrender::api::{LayoutPoint, LayoutSize, WorldPoint};
 use std::rc::Rc;
-use std::cell::RefCell;
-use std::sync::{Arc, Mutex};
-use futures::Stream;
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+use glutin;
+use futures::{future, Async, Poll, Stream};
+use futures::executor::NotifyHandle;
 use tokio_core::reactor::Core;
 
-pub struct Application<S: State> {
-    title: &'static str,
+/// Sent to the root component of the main window as the application starts and stops, and to the
+/// root component of a window when it gains or loses focus.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Lifecycle {
+    Started,
+    FocusGained,
+    FocusLost,
+    Suspended,
+    Resumed,
+    /// The event loop has stopped and the windows are about to be closed.
+    ShuttingDown,
+}
+
+impl_describe_debug!(Lifecycle);
+
+/// Sent to the root component of the main window when the user closes it. When no component
+/// handles it the application quits; a component that does handle it decides by having the state
+/// return an exit status, or vetoes closing by not doing so.
+#[derive(Clone, Debug)]
+pub struct CloseRequested;
+
+impl_describe_debug!(CloseRequested);
+
+type Message<S> = Box<FnMut(&mut Application<S>) + Send>;
+
+/// Posts events and state updates into a running application from other threads. Anything that
+/// is posted before `Application::run` is handled as soon as the application starts.
+pub struct Dispatcher<S: State> {
+    messages: Arc<Mutex<mpsc::Sender<Message<S>>>>,
+    wakeup: Arc<Mutex<Option<NotifyHandle>>>,
+}
+
+impl<S: State> Clone for Dispatcher<S> {
+    fn clone(&self) -> Self {
+        Dispatcher {
+            messages: self.messages.clone(),
+            wakeup: self.wakeup.clone(),
+        }
+    }
+}
+
+impl<S: State> Dispatcher<S> {
+    /// Sends an event to the root component of the main window.
+    pub fn post_event<E: Event + Send>(&self, event: E) {
+        let mut event = Some(event);
+        self.post(Box::new(move |application: &mut Application<S>| {
+            if let Some(event) = event.take() {
+                application.dispatch(0, &[], |_| event);
+            }
+        }));
+    }
+
+    /// Replaces the state with the one returned by `update`, which gets the current state.
+    pub fn post_update<F: FnOnce(S) -> S + Send + 'static>(&self, update: F) {
+        let mut update = Some(update);
+        self.post(Box::new(move |application: &mut Application<S>| {
+            if let Some(update) = update.take() {
+                let new_state = update(application.state.clone());
+                application.update_state(new_state, None);
+            }
+        }));
+    }
+
+    /// Makes the application quit with the given status.
+    pub fn quit(&self, status: i32) {
+        self.post(Box::new(move |application: &mut Application<S>| {
+            application.exit_status = Some(status);
+        }));
+    }
+
+    pub(crate) fn post(&self, message: Message<S>) {
+        // Nothing to do once the application is gone
+        if self.messages.lock().unwrap().send(message).is_err() {
+            return;
+        }
+
+        if let Some(ref wakeup) = *self.wakeup.lock().unwrap() {
+            wakeup.notify(0);
+        }
+    }
+}
+
+/// Everything the application keeps track of for one of its windows. The renderer is only there
+/// while the application runs; a headless application just lays out and dispatches.
+struct AppWindow {
+    key: Option<String>,
+    options: WindowOptions,
+    properties: WindowProperties,
+    /// What the window is known to have. Properties the user can change by hand are forgotten
+    /// when they might have changed, so that the next state update applies them again.
+    current_properties: WindowPropertyChanges,
+    /// Set when the state described the properties again since they were last applied.
+    properties_pending: bool,
+    tree: Arc<Mutex<Component>>,
     layout_context: Rc<RefCell<LayoutContext>>,
+    size: LayoutSize,
+    /// Set when the tree or size changed since the last layout pass.
+    layout_dirty: Cell<bool>,
+    /// Set when the tree or size changed since the last frame was submitted.
+    dirty: bool,
+    frames: usize,
+    closed: bool,
+    gestures: GestureRecognizer,
+    /// The component that captured the pointer, and the button whose press captured it.
+    pointer_capture: Option<(ComponentPath, Option<MouseButton>)>,
+    /// The pointer buttons that are held down in this window.
+    held_buttons: HashSet<Option<MouseButton>>,
+    touch_targets: HashMap<u64, ComponentPath>,
+    transitions: Transitions,
+    /// Whether the inspector overlay is shown, and the component it highlights.
+    inspecting: bool,
+    inspected: Option<ComponentPath>,
+    renderer: Option<RendererHandle>,
+}
+
+impl AppWindow {
+    fn new(key: Option<String>, options: WindowOptions, properties: WindowProperties, root: Component, gestures: &GestureConfig) -> AppWindow {
+        let size = LayoutSize::zero();
+        let layout_context = Rc::new(RefCell::new(LayoutContext::new()));
+        let mut transitions = Transitions::new();
+        transitions.update(&root);
+
+        AppWindow {
+            key,
+            options,
+            properties,
+            current_properties: WindowPropertyChanges::default(),
+            properties_pending: true,
+            tree: Arc::new(Mutex::new(root)),
+            layout_context,
+            size,
+            layout_dirty: Cell::new(true),
+            dirty: true,
+            frames: 0,
+            closed: false,
+            gestures: GestureRecognizer::new(gestures.clone()),
+            pointer_capture: None,
+            held_buttons: HashSet::new(),
+            touch_targets: HashMap::new(),
+            transitions,
+            inspecting: false,
+            inspected: None,
+            renderer: None,
+        }
+    }
+
+    fn id(&self) -> Option<WindowId> {
+        self.renderer.as_ref().map(|renderer| renderer.id())
+    }
+
+    fn open(&mut self, event_stream: &mut EventStream) {
+        if self.renderer.is_none() {
+            let renderer = WebrenderWindow::new(&self.options, self.layout_context.clone(), event_stream);
+            let size = renderer.layout_size();
+            self.renderer = Some(renderer);
+            self.resize(size);
+        }
+    }
+
+    fn close(&mut self) -> Option<WindowId> {
+        self.closed = true;
+        self.renderer.take().map(|renderer| {
+            let id = renderer.id();
+            renderer.close();
+            id
+        })
+    }
+
+    /// Without a renderer this only lays out, the way a frame would.
+    fn render(&mut self) {
+        if let Some(changes) = self.property_changes() {
+            if let Some(ref mut renderer) = self.renderer {
+                renderer.apply_window_properties(&changes);
+                self.current_properties.merge(&changes);
+                self.properties_pending = false;
+            }
+        }
+
+        // A frame that is still on its way to the screen already gets the next vsync, the
+        // changes since then are picked up once it has been presented
+        let frame_pending = self.renderer.as_ref().map_or(false, |renderer| renderer.frame_pending());
+        if self.dirty && !frame_pending {
+            self.ensure_layout();
+            if let Some(ref mut renderer) = self.renderer {
+                renderer.set_tree(self.tree.clone());
+                renderer.render();
+            }
+            self.frames += 1;
+            self.dirty = false;
+        }
+    }
+
+    fn resize(&mut self, size: LayoutSize) {
+        self.size = size;
+        self.current_properties.size = Some((size.width.round() as u32, size.height.round() as u32));
+        self.layout_dirty.set(true);
+        self.dirty = true;
+    }
+
+    fn set_properties(&mut self, properties: WindowProperties) {
+        self.properties = properties;
+        self.properties_pending = true;
+    }
+
+    /// The changes the next frame applies to the window, if the state described it since.
+    fn property_changes(&self) -> Option<WindowPropertyChanges> {
+        if self.properties_pending {
+            Some(window_property_changes(&self.properties, &self.current_properties))
+        } else {
+            None
+        }
+    }
+
+    fn set_tree(&mut self, root: Component) {
+        self.transitions.update(&root);
+        self.tree = Arc::new(Mutex::new(root));
+        self.apply_transitions();
+        self.layout_dirty.set(true);
+        self.dirty = true;
+        self.release_stale_pointer_capture();
+    }
+
+    fn advance_transitions(&mut self, elapsed: Duration) {
+        if self.transitions.is_active() {
+            self.transitions.advance(elapsed);
+            self.apply_transitions();
+            self.layout_dirty.set(true);
+            self.dirty = true;
+        }
+    }
+
+    fn apply_transitions(&mut self) {
+        let (styles, colors) = self.transitions.overrides(&self.tree.lock().unwrap());
+        let mut layout_context = self.layout_context.borrow_mut();
+        layout_context.set_style_overrides(styles);
+        layout_context.set_color_overrides(colors);
+    }
+
+    /// Lays out the tree if it changed, so that several state updates in a row only cost one
+    /// layout pass unless something needs the layout in between.
+    fn ensure_layout(&self) {
+        if self.layout_dirty.get() {
+            self.layout_context.borrow_mut().update_layout(&self.tree.lock().unwrap(), &self.size);
+            self.layout_dirty.set(false);
+            self.update_overlay();
+        }
+    }
+
+    /// Shows or hides the inspector overlay, highlighting the given component.
+    fn inspect(&mut self, inspecting: bool, inspected: Option<ComponentPath>) {
+        if inspecting == self.inspecting && inspected == self.inspected {
+            return;
+        }
+
+        self.inspecting = inspecting;
+        self.inspected = inspected;
+        self.dirty = true;
+        // Otherwise the next layout pass takes care of it
+        if !self.layout_dirty.get() {
+            self.update_overlay();
+        }
+    }
+
+    /// The overlay depends on the layout, so this has to follow every layout pass.
+    fn update_overlay(&self) {
+        let overlay = if self.inspecting {
+            let tree = self.tree.lock().unwrap();
+            inspector::overlay(&tree, &self.layout_context.borrow(), self.inspected.as_ref().map(|path| &path[..]), &self.size)
+        } else {
+            Vec::new()
+        };
+        self.layout_context.borrow_mut().set_overlay(overlay);
+    }
+
+    fn describe(&self) -> String {
+        self.ensure_layout();
+        let tree = self.tree.lock().unwrap();
+        inspector::describe(&tree, &self.layout_context.borrow(), self.inspected.as_ref().map(|path| &path[..]), &self.size)
+    }
+
+    fn hit_test(&self, point: WorldPoint) -> Option<ComponentPath> {
+        self.ensure_layout();
+        self.layout_context.borrow().find_path_at(point, &self.tree.lock().unwrap())
+    }
+
+    fn captures_pointer(&self, path: &[usize]) -> bool {
+        self.tree.lock().unwrap().find_by_path(path).map_or(false, |node| node.inspect().captures_pointer())
+    }
+
+    /// Releases the pointer capture once the captured component no longer asks for it.
+    fn release_stale_pointer_capture(&mut self) {
+        let captured = match self.pointer_capture {
+            Some((ref path, _)) => self.captures_pointer(path),
+            None => return
+        };
+
+        if !captured {
+            self.pointer_capture = None;
+        }
+    }
+}
+
+pub struct Application<S: State> {
     state: S,
+    /// The main window comes first, followed by the child windows described by the state.
+    windows: Vec<AppWindow>,
+    gesture_config: GestureConfig,
+    /// Child windows that were closed by the user while the state still describes them.
+    dismissed_windows: HashSet<String>,
+    closed_window_ids: Vec<WindowId>,
+    /// The running subscriptions by key.
+    subscriptions: HashMap<String, Box<Source<S>>>,
+    /// Wakes up the event loop when a subscription stream has an item.
+    notify: NotifyHandle,
+    dispatcher: Dispatcher<S>,
+    messages: mpsc::Receiver<Message<S>>,
+    /// Set once the application should quit.
+    exit_status: Option<i32>,
+    history: Option<History<S>>,
+    undo: Option<UndoStack<S>>,
+    /// Whether a transaction is open because a pointer button is held in any window.
+    pointer_transaction: bool,
+    middlewares: Vec<Middleware<S>>,
+    persistence: Option<Persistence<S>>,
+    /// The main window geometry that was restored along with the state.
+    restored_geometry: Option<WindowGeometry>,
+    recorder: Option<Recorder>,
+    /// Seeds the ids of the components of every build, see `seed_component_ids`.
+    component_id_seed: Option<u64>,
+    /// Set while `run` drives the event loop.
+    running: bool,
+    /// Set when a transition still has to be saved, see `save_on_change`.
+    save_pending: bool,
+    /// Formats the state for the inspector. Only set when the inspector is enabled.
+    describe_state: Option<Box<Fn(&S) -> String>>,
+    inspecting: bool,
+    /// The modifier keys that are held, tracked from the keyboard events for the shortcuts.
+    modifiers: Modifiers,
 }
 
 impl<S: State> Application<S> {
     pub fn new(title: &'static str, state: S) -> Self {
-        let layout_context = Rc::new(RefCell::new(LayoutContext::new()));
+        let gesture_config = GestureConfig::default();
+        let main_window = AppWindow::new(None, WindowOptions::new(title), state.window(), state.build(), &gesture_config);
 
-        Application {
-            title,
-            layout_context,
+        let (messages_tx, messages_rx) = mpsc::channel();
+        let dispatcher = Dispatcher {
+            messages: Arc::new(Mutex::new(messages_tx)),
+            wakeup: Arc::new(Mutex::new(None)),
+        };
+
+        let mut application = Application {
             state,
+            windows: vec![main_window],
+            gesture_config,
+            dismissed_windows: HashSet::new(),
+            closed_window_ids: Vec::new(),
+            subscriptions: HashMap::new(),
+            notify: subscription::noop_notify_handle(),
+            dispatcher,
+            messages: messages_rx,
+            exit_status: None,
+            history: None,
+            undo: None,
+            pointer_transaction: false,
+            middlewares: Vec::new(),
+            persistence: None,
+            restored_geometry: None,
+            recorder: None,
+            component_id_seed: None,
+            running: false,
+            save_pending: false,
+            describe_state: None,
+            inspecting: false,
+            modifiers: Modifiers::none(),
+        };
+        let children = application.state.windows();
+        application.sync_child_windows(children);
+        application.sync_subscriptions();
+        application
+    }
+
+    /// Options for the main window. Options with an empty title, such as the default ones, keep
+    /// the title passed to `new`.
+    pub fn window(mut self, options: WindowOptions) -> Self {
+        let mut options = options;
+        if options.title.is_empty() {
+            options.title = self.windows[0].options.title.clone();
         }
+        self.windows[0].options = options;
+        self.apply_restored_geometry();
+        self
     }
 
-    pub fn run(mut self) {
-        let (mut renderer, event_stream) = WebrenderWindow::new(self.title, self.layout_context.clone());
+    /// Restores the state and main window geometry that were saved by an earlier session, and
+    /// saves them again when the application quits. A state that can't be restored is logged and
+    /// the initial state is kept.
+    pub fn persistence(mut self, persistence: Persistence<S>) -> Self {
+        match persistence.load() {
+            Ok(Some(saved)) => {
+                self.restored_geometry = saved.geometry;
+                self.apply_restored_geometry();
+                if saved.state.exit_status().is_some() {
+                    warn!("The restored state asks to quit, the next state transition will quit");
+                }
+                self.apply_state(saved.state);
+                // A session that was saved while quitting starts again instead of quitting right away
+                self.exit_status = None;
+                // The initial state was never shown, so history starts at the restored one
+                if self.history.is_some() {
+                    self.history = Some(History::new(self.state.clone()));
+                }
+            },
+            Ok(None) => {},
+            Err(e) => warn!("Could not restore the saved state: {}", e)
+        }
+        self.persistence = Some(persistence);
+        self
+    }
 
-        let new_state = self.state.clone();
-        let mut tree = self.update_tree(&mut renderer, new_state);
+    /// Writes every input the windows receive to the recorder, so that the session can be replayed
+    /// with `Recording::replay`. Unless `seed_component_ids` was used, component ids are seeded
+    /// the way a replay seeds them.
+    pub fn record(mut self, recorder: Recorder) -> Self {
+        self.recorder = Some(recorder);
+        self.ensure_seeded_component_ids();
+        self
+    }
 
-        let event_logger = event_stream.for_each(|event| {
-            //println!("event: {:?}", event);
-            match event {
-                WindowEvent::Interaction(_, _) => {
-                    let new_tree = match self.handle_interaction(event, &tree.lock().unwrap()) {
-                        Ok(new_state) => {
-                            Some(self.update_tree(&mut renderer, new_state))
-                        },
-                        Err(_) => {
-                            None
-                        }
+    /// Builds the component trees with `Component::build_seeded`, so that the same states give
+    /// ids that read the same on every run.
+    pub fn seed_component_ids(mut self, seed: u64) -> Self {
+        self.component_id_seed = Some(seed);
+        self.rebuild();
+        self
+    }
+
+    /// Seeds component ids with 0, unless they are seeded already. Recording and replaying do this
+    /// so that the ids of a replayed session match the recorded one.
+    pub(crate) fn ensure_seeded_component_ids(&mut self) {
+        if self.component_id_seed.is_none() {
+            self.component_id_seed = Some(0);
+            self.rebuild();
+        }
+    }
+
+    /// Enables the inspector, which is toggled with F12 or `toggle_inspector`. While it is shown,
+    /// each window highlights the layout box, padding and margins of the component under the
+    /// pointer, and outlines its component tree on the right. Names, styles, event types and the
+    /// state can't be drawn, so they are logged when the inspector is shown and again on
+    /// Shift+F12 for the component under the pointer, see `inspector_text`.
+    pub fn inspector(mut self) -> Self where S: fmt::Debug {
+        self.describe_state = Some(Box::new(|state: &S| format!("{:#?}", state)));
+        self
+    }
+
+    /// Answers the requests of remote inspection clients, see `RemoteServer`. Requests are handled
+    /// while the application runs, or by `poll_dispatched` in a headless application.
+    pub fn remote(self, server: RemoteServer) -> Self where S: fmt::Debug {
+        server.start(self.dispatcher());
+        self
+    }
+
+    /// Captures the contents of the main window, or the child window with the given key. None
+    /// when the window isn't open, e.g. in a headless application.
+    pub fn screenshot(&mut self, window: Option<&str>) -> Option<Screenshot> {
+        let index = self.window_index(window)?;
+        self.windows[index].renderer.as_mut().map(|renderer| renderer.screenshot())
+    }
+
+    /// Calls `f` with the laid out tree of the main window, or the child window with the given key.
+    pub(crate) fn with_window<R, F: FnOnce(&Component, &LayoutContext) -> R>(&self, window: Option<&str>, f: F) -> Option<R> {
+        let window = &self.windows[self.window_index(window)?];
+        window.ensure_layout();
+        let tree = window.tree.lock().unwrap();
+        let layout_context = window.layout_context.borrow();
+        Some(f(&tree, &layout_context))
+    }
+
+    /// Shows or hides the inspector, if it is enabled.
+    pub fn toggle_inspector(&mut self) {
+        if self.describe_state.is_none() {
+            return;
+        }
+
+        self.inspecting = !self.inspecting;
+        let inspecting = self.inspecting;
+        for window in &mut self.windows {
+            window.inspect(inspecting, None);
+        }
+        if inspecting {
+            self.log_inspector();
+        }
+    }
+
+    /// What the inspector shows: the component tree of the window with the hovered component, or
+    /// of the main window, the details of the hovered component and the state. None while the
+    /// inspector is hidden.
+    pub fn inspector_text(&self) -> Option<String> {
+        let describe_state = match self.describe_state {
+            Some(ref describe_state) if self.inspecting => describe_state,
+            _ => return None
+        };
+
+        let window = self.windows.iter().find(|window| !window.closed && window.inspected.is_some()).unwrap_or(&self.windows[0]);
+        Some(format!("{}\nstate: {}\n", window.describe(), describe_state(&self.state)))
+    }
+
+    fn log_inspector(&self) {
+        if let Some(text) = self.inspector_text() {
+            info!("Inspector:\n{}", text);
+        }
+    }
+
+    /// Saves the state and main window geometry, if persistence is enabled.
+    pub fn save(&self) {
+        if let Some(ref persistence) = self.persistence {
+            let geometry = self.window_geometry();
+            if let Err(e) = persistence.save(&self.state, geometry.as_ref()) {
+                warn!("Could not save the state: {}", e);
+            }
+        }
+    }
+
+    pub fn gestures(mut self, config: GestureConfig) -> Self {
+        for window in &mut self.windows {
+            window.gestures = GestureRecognizer::new(config.clone());
+        }
+        self.gesture_config = config;
+        self
+    }
+
+    /// Records every state transition, see `history()`. Meant for debugging, since every state is
+    /// kept around.
+    pub fn record_history(mut self, record: bool) -> Self {
+        self.history = if record {
+            Some(History::new(self.state.clone()))
+        } else {
+            None
+        };
+        self
+    }
+
+    /// The recorded states, when `record_history` is enabled.
+    pub fn history(&self) -> Option<&History<S>> {
+        self.history.as_ref()
+    }
+
+    /// Shows the recorded state at `position` as if the application was in it. Returns false when
+    /// there is no such state.
+    pub fn jump_to(&mut self, position: usize) -> bool {
+        let state = match self.history {
+            Some(ref mut history) => history.jump_to(position),
+            None => None
+        };
+
+        match state {
+            Some(state) => {
+                self.apply_state(state);
+                self.save_on_change();
+                true
+            },
+            None => false
+        }
+    }
+
+    pub fn step_back(&mut self) -> bool {
+        match self.history.as_ref().map(|history| history.position()) {
+            Some(position) if position > 0 => self.jump_to(position - 1),
+            _ => false
+        }
+    }
+
+    pub fn step_forward(&mut self) -> bool {
+        match self.history.as_ref().map(|history| history.position()) {
+            Some(position) => self.jump_to(position + 1),
+            None => false
+        }
+    }
+
+    /// Adds a middleware to the end of the chain that every state transition passes through
+    /// before it is applied. Going back to a state that was already applied once, with undo, redo
+    /// or `jump_to`, and restoring a saved state with `persistence` skip the chain.
+    pub fn middleware(mut self, middleware: Middleware<S>) -> Self {
+        self.middlewares.push(middleware);
+        self
+    }
+
+    /// Keeps up to `limit` steps to undo, see `State::undo_step()`. Ctrl+Z undoes and Ctrl+Shift+Z
+    /// redoes, and everything that happens while a pointer button is held is a single step.
+    pub fn undo_history(mut self, limit: usize) -> Self {
+        self.undo = Some(UndoStack::new(limit));
+        self
+    }
+
+    /// Goes back to the state before the last undo step. Returns false when there is nothing to
+    /// undo.
+    pub fn undo(&mut self) -> bool {
+        let state = match self.undo {
+            Some(ref mut undo) => undo.undo(self.state.clone()),
+            None => None
+        };
+        self.restore_state(state)
+    }
+
+    /// Reapplies the step that was undone last. Returns false when there is nothing to redo.
+    pub fn redo(&mut self) -> bool {
+        let state = match self.undo {
+            Some(ref mut undo) => undo.redo(self.state.clone()),
+            None => None
+        };
+        self.restore_state(state)
+    }
+
+    pub fn can_undo(&self) -> bool {
+        self.undo.as_ref().map_or(false, |undo| undo.can_undo())
+    }
+
+    pub fn can_redo(&self) -> bool {
+        self.undo.as_ref().map_or(false, |undo| undo.can_redo())
+    }
+
+    /// Makes all transitions until the matching `end_transaction` a single undo step.
+    /// Transactions can be nested.
+    pub fn begin_transaction(&mut self) {
+        if let Some(ref mut undo) = self.undo {
+            undo.begin_transaction();
+        }
+    }
+
+    pub fn end_transaction(&mut self) {
+        if let Some(ref mut undo) = self.undo {
+            undo.end_transaction();
+        }
+    }
+
+    pub fn state(&self) -> &S {
+        &self.state
+    }
+
+    /// The component tree of the main window.
+    pub fn tree(&self) -> MutexGuard<Component> {
+        // Laying out needs the tree, so it can't happen while the caller holds on to it
+        self.windows[0].ensure_layout();
+        self.windows[0].tree.lock().unwrap()
+    }
+
+    /// The layout of the main window.
+    pub fn layout_context(&self) -> &Rc<RefCell<LayoutContext>> {
+        self.windows[0].ensure_layout();
+        &self.windows[0].layout_context
+    }
+
+    /// The changes to the title, size and fullscreen mode of the main window, or the child window
+    /// with the given key, that the next frame applies. None when the state didn't describe the
+    /// window since they were last applied.
+    pub fn window_property_changes(&self, window: Option<&str>) -> Option<WindowPropertyChanges> {
+        self.windows[self.window_index(window)?].property_changes()
+    }
+
+    /// The keys of the child windows that are currently open.
+    pub fn child_windows(&self) -> Vec<&str> {
+        self.windows.iter()
+            .filter(|window| !window.closed)
+            .filter_map(|window| window.key.as_ref().map(|key| key.as_str()))
+            .collect()
+    }
+
+    /// Runs the event loop until the application quits, and returns its exit status.
+    pub fn run(mut self) -> i32 {
+        self.running = true;
+        let mut core = Core::new().unwrap();
+        let mut event_stream = EventStream::new(self.gesture_config.clone(), &core.handle());
+        self.notify = event_stream.notify_handle();
+        *self.dispatcher.wakeup.lock().unwrap() = Some(event_stream.notify_handle());
+        // Streams that were started and messages that were posted before only get handled from
+        // here on
+        self.poll_subscriptions(Instant::now());
+        self.poll_dispatched();
+        self.dispatch(0, &[], |_| Lifecycle::Started);
+        self.purge_closed_windows();
+        self.sync_open_windows(&mut event_stream);
+        self.render();
+
+        if self.exit_status.is_none() {
+            let mut deadline = None;
+            let mut last_frame = None;
+            let event_loop = future::poll_fn(|| -> Poll<(), ()> {
+                loop {
+                    let (window_id, event) = match event_stream.poll()? {
+                        Async::Ready(Some(event)) => event,
+                        Async::Ready(None) => return Ok(Async::Ready(())),
+                        Async::NotReady => return Ok(Async::NotReady)
                     };
 
-                    if let Some(t) = new_tree {
-                        tree = t;
+                    //println!("event: {:?}", event);
+                    self.handle_window_event(window_id, event);
+                    self.poll_subscriptions(Instant::now());
+                    self.poll_dispatched();
+
+                    if self.exit_status.is_some() {
+                        return Ok(Async::Ready(()));
+                    }
+
+                    self.sync_open_windows(&mut event_stream);
+
+                    // Handle everything that has queued up before producing frames, so that a
+                    // burst of events results in a single layout and display list
+                    if event_stream.has_pending() {
+                        continue;
+                    }
+
+                    let animating = self.animating();
+                    if animating {
+                        // Frames are paced by their presentation, so only advance once the
+                        // previous one made it to the screen
+                        if !self.frame_pending() {
+                            let now = Instant::now();
+                            let elapsed = last_frame.map_or(Duration::from_millis(0), |last| now.duration_since(last));
+                            last_frame = Some(now);
+                            self.advance(elapsed);
+                            self.sync_open_windows(&mut event_stream);
+                        }
+                    } else {
+                        last_frame = None;
+                    }
+
+                    self.save_pending_changes();
+                    self.render();
+
+                    // Nothing changed on screen, so no presentation will wake us up for the next
+                    // animation frame
+                    if animating && !self.frame_pending() {
+                        event_stream.wake_at(Instant::now() + Duration::from_millis(16));
+                    }
+
+                    // The stream sleeps until something happens, so ask it to wake us up for
+                    // gestures that are recognized by time alone
+                    let next_deadline = self.next_deadline();
+                    if next_deadline != deadline {
+                        if let Some(next_deadline) = next_deadline {
+                            event_stream.wake_at(next_deadline);
+                        }
+                        deadline = next_deadline;
                     }
+                }
+            });
+
+            let _ = core.run(event_loop);
+        }
+
+        self.dispatch(0, &[], |_| Lifecycle::ShuttingDown);
+        *self.dispatcher.wakeup.lock().unwrap() = None;
+        // Before closing the windows, while their position can still be queried
+        self.save();
+
+        for window in &mut self.windows {
+            window.close();
+        }
+
+        self.exit_status.unwrap_or(0)
+    }
 
-                    Ok(())
+    /// Lays out the main window at the given size.
+    pub fn resize(&mut self, size: LayoutSize) {
+        self.windows[0].resize(size);
+    }
+
+    /// Handles an event for the main window the same way `run` does, without needing a window.
+    /// This allows driving the application with synthetic events, after `resize` has given it a
+    /// layout.
+    pub fn inject(&mut self, event: WindowEvent) {
+        self.handle_event(0, event);
+    }
+
+    /// Like `inject`, but for the child window with the given key.
+    pub fn inject_into(&mut self, key: &str, event: WindowEvent) {
+        if let Some(index) = self.child_window_index(key) {
+            self.handle_event(index, event);
+        }
+    }
+
+    /// Handles a recorded input for the main window, or the child window with the given key.
+    pub(crate) fn replay_input(&mut self, window: Option<&str>, input: RecordedInput) {
+        let index = match self.window_index(window) {
+            Some(index) => index,
+            None => return
+        };
+
+        match input {
+            RecordedInput::Resized(size) => self.handle_event(index, WindowEvent::Resized(size)),
+            RecordedInput::Interaction(interaction) => self.handle_event(index, WindowEvent::Interaction(interaction.window_position(), interaction)),
+            RecordedInput::Touch(touch) => self.handle_event(index, WindowEvent::Touch(touch)),
+            RecordedInput::Closed => self.handle_event(index, WindowEvent::WindowClosed),
+            RecordedInput::Key(input) => {
+                // Glutin keyboard events can't be created outside of glutin, so keys skip the
+                // event and go straight to their handling
+                if let Some(ref mut recorder) = self.recorder {
+                    recorder.record(window, &RecordedInput::Key(input));
                 }
-                WindowEvent::NotifyRenderComplete => {
+                self.handle_key(input);
+            }
+        }
+    }
+
+    fn child_window_index(&self, key: &str) -> Option<usize> {
+        self.windows.iter().position(|window| !window.closed && window.key.as_ref().map_or(false, |k| k == key))
+    }
+
+    /// The main window for None, otherwise the child window with the given key.
+    fn window_index(&self, window: Option<&str>) -> Option<usize> {
+        match window {
+            Some(key) => self.child_window_index(key),
+            None => Some(0)
+        }
+    }
+
+    /// Advances the transitions in every window and sends an `AnimationFrame` to their root
+    /// components, as if `elapsed` passed since the previous frame. `run` does this on every frame
+    /// while the application is animating; without a window this drives animations on a virtual
+    /// clock.
+    pub fn advance(&mut self, elapsed: Duration) {
+        for window in &mut self.windows {
+            window.advance_transitions(elapsed);
+        }
+
+        for index in 0..self.windows.len() {
+            if !self.windows[index].closed {
+                self.dispatch(index, &[], |_| AnimationFrame { elapsed });
+            }
+        }
+
+        self.purge_closed_windows();
+    }
+
+    /// Asks the application to quit, the same way closing the main window does. See
+    /// `CloseRequested`.
+    pub fn request_close(&mut self) {
+        let (handled, cause) = {
+            let root = self.windows[0].tree.lock().unwrap();
+            let cause = if self.wants_causes() {
+                Some(Cause::new(&CloseRequested, Some(ComponentInfo::new(root.inspect(), &[]))))
+            } else {
+                None
+            };
+            (root.invoke(&self.state, CloseRequested), cause)
+        };
+
+        match handled {
+            Ok(new_state) => self.update_state(new_state, cause),
+            Err(_) => {
+                if self.exit_status.is_none() {
+                    self.exit_status = Some(0);
+                }
+            }
+        }
+    }
+
+    /// The status the application quits with, once it has been asked to quit.
+    pub fn exit_status(&self) -> Option<i32> {
+        self.exit_status
+    }
+
+    /// A handle for posting events and state updates from other threads, see `Dispatcher`.
+    pub fn dispatcher(&self) -> Dispatcher<S> {
+        self.dispatcher.clone()
+    }
+
+    /// Handles the events and state updates posted through a `Dispatcher`. `run` does this
+    /// whenever a dispatcher wakes it up.
+    pub fn poll_dispatched(&mut self) {
+        loop {
+            let mut message = match self.messages.try_recv() {
+                Ok(message) => message,
+                Err(_) => break
+            };
+            message(self);
+        }
+
+        self.purge_closed_windows();
+    }
+
+    /// Fires the timers that are due at `now` and feeds the items that subscription streams have
+    /// ready through their handlers, up to `subscription::MAX_ITEMS_PER_POLL` per subscription.
+    /// `run` does this after every event, and comes back for the remaining items after handling
+    /// the other events; without a window this drives the subscriptions on a virtual clock.
+    pub fn poll_subscriptions(&mut self, now: Instant) {
+        let keys: Vec<String> = self.subscriptions.keys().cloned().collect();
+        for key in keys {
+            // A handler can update the state in a way that stops its own subscription
+            for handled in 0.. {
+                if handled == subscription::MAX_ITEMS_PER_POLL {
+                    // Come back for the rest once the other events had their turn
+                    self.notify.notify(0);
+                    break;
+                }
+
+                let result = match self.subscriptions.get_mut(&key) {
+                    Some(source) => source.next(&self.state, now, &self.notify),
+                    None => break
+                };
+
+                match result {
+                    Some(Ok(new_state)) => self.update_state(new_state, None),
+                    Some(Err(_)) => {},
+                    None => break
+                }
+            }
+        }
+
+        self.purge_closed_windows();
+    }
+
+    /// Whether the state or any of the transitions in the windows is animating.
+    pub fn animating(&self) -> bool {
+        self.state.animating() || self.windows.iter().any(|window| window.transitions.is_active())
+    }
+
+    fn frame_pending(&self) -> bool {
+        self.windows.iter().any(|window| window.renderer.as_ref().map_or(false, |renderer| renderer.frame_pending()))
+    }
+
+    fn handle_window_event(&mut self, window_id: Option<WindowId>, event: WindowEvent) {
+        let index = match window_id {
+            Some(id) => match self.windows.iter().position(|window| window.id() == Some(id)) {
+                Some(index) => index,
+                None => return
+            },
+            None => 0
+        };
+
+        match event {
+            WindowEvent::NotifyRenderComplete |
+            WindowEvent::GlutinWindowEvent(glutin::WindowEvent::Refresh) => {
+                if let Some(ref mut renderer) = self.windows[index].renderer {
                     renderer.update();
-                    Ok(())
                 }
-                WindowEvent::ApplicationClosed => {
-                    //renderer.stop();
-                    Err(())
+            }
+            WindowEvent::Timer => {
+                self.tick();
+            }
+            WindowEvent::Resized(_) => {
+                let size = self.windows[index].renderer.as_mut().map(|renderer| {
+                    renderer.update_window_size();
+                    renderer.layout_size()
+                });
+
+                if let Some(size) = size {
+                    self.handle_event(index, WindowEvent::Resized(size));
                 }
-                _ => Ok(())
             }
-        });
+            WindowEvent::GlutinWindowEvent(glutin::WindowEvent::Moved(_, _)) => {
+                // Moving to another monitor can change the DPI factor
+                let size = self.windows[index].renderer.as_mut().and_then(|renderer| {
+                    if renderer.update_window_size() {
+                        Some(renderer.layout_size())
+                    } else {
+                        None
+                    }
+                });
 
-        let mut core = Core::new().unwrap();
-        let _ = core.run(event_logger);
+                if let Some(size) = size {
+                    self.handle_event(index, WindowEvent::Resized(size));
+                }
+            }
+            event => {
+                self.handle_event(index, event);
+            }
+        }
     }
 
-    fn handle_interaction(&self, event: WindowEvent, tree: &Component) -> Result<S, InvocationError> {
+    fn handle_event(&mut self, index: usize, event: WindowEvent) {
+        if let Some(ref mut recorder) = self.recorder {
+            if let Some(input) = RecordedInput::from_event(&event) {
+                recorder.record(self.windows[index].key.as_ref().map(String::as_str), &input);
+            }
+        }
+
+        let long_presses = self.windows[index].gestures.tick(Instant::now());
+        self.dispatch_gestures(index, long_presses);
+
         match event {
-            WindowEvent::Interaction(point, interaction) => {
-                let lc = self.layout_context.borrow();
-                if let Some(node) = lc.find_node_at(point, &tree) {
-                    println!("Interaction for: {:?}", node);
-                    node.invoke(&self.state, interaction)
-                } else {
-                    Err(InvocationError)
+            WindowEvent::Resized(size) => {
+                self.windows[index].resize(size);
+                self.dispatch(index, &[], |_| WindowResized { size });
+            },
+            WindowEvent::Interaction(point, interaction) => self.handle_interaction(index, point, interaction),
+            WindowEvent::Touch(touch) => self.handle_touch(index, touch),
+            WindowEvent::WindowClosed if index == 0 => self.request_close(),
+            WindowEvent::WindowClosed => self.close_child_window(index),
+            WindowEvent::GlutinWindowEvent(glutin::WindowEvent::KeyboardInput { input, .. }) => self.handle_key(input),
+            WindowEvent::GlutinWindowEvent(glutin::WindowEvent::Focused(focused)) => {
+                // The user may have left fullscreen by hand
+                self.windows[index].current_properties.fullscreen = None;
+                // Releases may never arrive once another window has the focus
+                if !focused {
+                    self.windows[index].pointer_capture = None;
+                    self.windows[index].held_buttons.clear();
+                    self.update_pointer_transaction();
                 }
+                self.dispatch(index, &[], |_| if focused { Lifecycle::FocusGained } else { Lifecycle::FocusLost });
+            },
+            WindowEvent::GlutinEvent(glutin::Event::Suspended(suspended)) => {
+                self.dispatch(0, &[], |_| if suspended { Lifecycle::Suspended } else { Lifecycle::Resumed });
+            },
+            _ => {}
+        }
+
+        self.purge_closed_windows();
+    }
+
+    /// Reports long-presses in all windows that have been held long enough.
+    fn tick(&mut self) {
+        let now = Instant::now();
+        for index in 0..self.windows.len() {
+            if !self.windows[index].closed {
+                let long_presses = self.windows[index].gestures.tick(now);
+                self.dispatch_gestures(index, long_presses);
+            }
+        }
+
+        self.purge_closed_windows();
+    }
+
+    fn next_deadline(&self) -> Option<Instant> {
+        let gestures = self.windows.iter().filter_map(|window| window.gestures.next_deadline());
+        let subscriptions = self.subscriptions.values().filter_map(|source| source.deadline());
+        gestures.chain(subscriptions).min()
+    }
+
+    fn handle_key(&mut self, input: glutin::KeyboardInput) {
+        self.modifiers.update(&input);
+        if input.state != glutin::ElementState::Pressed {
+            return;
+        }
+
+        let modifiers = self.modifiers;
+        if input.virtual_keycode == Some(glutin::VirtualKeyCode::F12) && self.describe_state.is_some() {
+            if self.inspecting && modifiers.shift {
+                self.log_inspector();
+            } else {
+                self.toggle_inspector();
             }
-            _ => {
-                Err(InvocationError)
+            return;
+        }
+
+        if self.undo.is_none() {
+            return;
+        }
+
+        if input.virtual_keycode == Some(glutin::VirtualKeyCode::Z) && (modifiers.ctrl || modifiers.logo) {
+            if modifiers.shift {
+                self.redo();
+            } else {
+                self.undo();
             }
         }
     }
 
-    fn update_tree(&mut self, renderer: &mut RendererHandle, new_state: S) -> Arc<Mutex<Component>> {
-        let tree = Arc::new(Mutex::new(new_state.build()));
-        renderer.set_tree(tree.clone());
-        renderer.render();
+    fn handle_interaction(&mut self, index: usize, point: WorldPoint, interaction: Interaction) {
+        match interaction {
+            Interaction::Pressed(ref e) => {
+                self.windows[index].held_buttons.insert(e.button);
+            },
+            Interaction::Released(ref e) => {
+                self.windows[index].held_buttons.remove(&e.button);
+            },
+            _ => {}
+        }
+        // The transaction of the release is only closed after the release has been handled
+        if let Interaction::Pressed(_) = interaction {
+            self.update_pointer_transaction();
+        }
+
+        let hit = self.windows[index].hit_test(point);
+        if self.inspecting {
+            if let Interaction::Moved(_) = interaction {
+                // Only one window highlights a component at a time
+                for (i, window) in self.windows.iter_mut().enumerate() {
+                    let inspected = if i == index { hit.clone() } else { None };
+                    window.inspect(true, inspected);
+                }
+            }
+        }
+
+        let gestures = self.windows[index].gestures.handle(hit.as_ref(), &interaction);
+
+        let target = match interaction {
+            Interaction::Moved(_) | Interaction::Released(_) => self.windows[index].pointer_capture.as_ref().map(|&(ref path, _)| path.clone()).or(hit),
+            _ => hit
+        };
+
+        if let Some(target) = target {
+            self.dispatch(index, &target, |origin| interaction.clone().localize(origin));
+
+            // Other buttons can be pressed and released during a drag without ending it
+            let window = &mut self.windows[index];
+            match interaction {
+                Interaction::Pressed(ref e) if window.pointer_capture.is_none() => {
+                    if window.captures_pointer(&target) {
+                        window.pointer_capture = Some((target, e.button));
+                    }
+                },
+                Interaction::Released(ref e) => {
+                    if window.pointer_capture.as_ref().map_or(false, |&(_, button)| button == e.button) {
+                        window.pointer_capture = None;
+                    }
+                },
+                _ => {}
+            }
+        }
+        self.dispatch_gestures(index, gestures);
+
+        if let Interaction::Released(_) = interaction {
+            self.update_pointer_transaction();
+        }
+    }
+
+    /// A drag, or anything else that happens while a pointer button is held in any window, is
+    /// undone at once. Buttons held in several windows share a single transaction.
+    fn update_pointer_transaction(&mut self) {
+        let held = self.windows.iter().any(|window| !window.closed && !window.held_buttons.is_empty());
+        if held != self.pointer_transaction {
+            self.pointer_transaction = held;
+            if held {
+                self.begin_transaction();
+            } else {
+                self.end_transaction();
+            }
+        }
+    }
+
+    /// Every finger is hit-tested when it goes down, and keeps sending its events to that
+    /// component until it is lifted.
+    fn handle_touch(&mut self, index: usize, mut touch: TouchEvent) {
+        let target = match touch.phase {
+            TouchPhase::Started => {
+                let hit = self.windows[index].hit_test(touch.window_position);
+                if let Some(ref hit) = hit {
+                    self.windows[index].touch_targets.insert(touch.id, hit.clone());
+                }
+                hit
+            },
+            TouchPhase::Moved => self.windows[index].touch_targets.get(&touch.id).cloned(),
+            TouchPhase::Ended | TouchPhase::Cancelled => self.windows[index].touch_targets.remove(&touch.id),
+        };
+
+        let gestures = self.windows[index].gestures.handle_touch(target.as_ref(), &touch);
+        if let Some(target) = target {
+            self.dispatch(index, &target, |origin| {
+                touch.localize(origin);
+                touch
+            });
+        }
+        self.dispatch_gestures(index, gestures);
+    }
+
+    fn dispatch_gestures(&mut self, index: usize, gestures: Vec<(ComponentPath, Gesture)>) {
+        for (target, gesture) in gestures {
+            self.dispatch(index, &target, |origin| gesture.localize(origin));
+        }
+    }
+
+    /// Invokes the component at `target` in the given window with the event built for its origin,
+    /// and rebuilds all windows if the component handled it.
+    fn dispatch<E: Event, F: FnOnce(&LayoutPoint) -> E>(&mut self, index: usize, target: &[usize], event: F) {
+        let wants_causes = self.wants_causes();
+        let result = {
+            let window = &self.windows[index];
+            window.ensure_layout();
+            let tree = window.tree.lock().unwrap();
+            match tree.find_by_path(target) {
+                Some(node) => {
+                    debug!("Event for: {:?}", node);
+                    let origin = window.layout_context.borrow().absolute_bounds(node.inspect()).origin;
+                    let event = event(&origin);
+                    let cause = if wants_causes {
+                        Some(Cause::new(&event, Some(ComponentInfo::new(node.inspect(), target))))
+                    } else {
+                        None
+                    };
+                    node.invoke(&self.state, event).map(|new_state| (new_state, cause))
+                },
+                None => Err(InvocationError)
+            }
+        };
+
+        if let Ok((new_state, cause)) = result {
+            self.update_state(new_state, cause);
+        }
+    }
+
+    /// Describing causes formats every event, so it only happens when something looks at them.
+    fn wants_causes(&self) -> bool {
+        self.history.is_some() || !self.middlewares.is_empty()
+    }
+
+    fn update_state(&mut self, new_state: S, cause: Option<Cause>) {
+        let mut new_state = new_state;
+        {
+            let change = StateChange {
+                previous: &self.state,
+                cause: cause.as_ref(),
+            };
+
+            for middleware in &mut self.middlewares {
+                new_state = match (**middleware)(&change, new_state) {
+                    Ok(new_state) => new_state,
+                    Err(_) => {
+                        debug!("State transition rejected by middleware: {:?}", cause);
+                        return;
+                    }
+                };
+            }
+        }
+
+        if let Some(ref mut undo) = self.undo {
+            undo.record(self.state.clone(), new_state.undo_step(&self.state));
+        }
+
+        if let Some(ref mut history) = self.history {
+            history.record(new_state.clone(), cause);
+        }
+
+        self.apply_state(new_state);
+        self.save_on_change();
+    }
+
+    /// While running, a burst of transitions such as a drag is saved once, right before the next
+    /// frame, instead of writing the file for every one of them.
+    fn save_on_change(&mut self) {
+        if self.persistence.as_ref().map_or(false, |persistence| persistence.saves_on_change()) {
+            if self.running {
+                self.save_pending = true;
+            } else {
+                self.save();
+            }
+        }
+    }
+
+    fn save_pending_changes(&mut self) {
+        if self.save_pending {
+            self.save_pending = false;
+            self.save();
+        }
+    }
+
+    fn apply_restored_geometry(&mut self) {
+        if let Some(ref geometry) = self.restored_geometry {
+            let options = &mut self.windows[0].options;
+            options.size = Some(geometry.size);
+            if geometry.position.is_some() {
+                options.position = geometry.position;
+            }
+        }
+    }
+
+    /// The geometry of the main window. Without an open window, the size it was laid out at is
+    /// used, or the restored geometry when it was never laid out.
+    fn window_geometry(&self) -> Option<WindowGeometry> {
+        let window = &self.windows[0];
+        if window.size.width <= 0.0 || window.size.height <= 0.0 {
+            return self.restored_geometry.clone();
+        }
+
+        let position = match window.renderer {
+            Some(ref renderer) => renderer.position(),
+            None => self.restored_geometry.as_ref().and_then(|geometry| geometry.position)
+        };
+        Some(WindowGeometry {
+            size: (window.size.width as u32, window.size.height as u32),
+            position,
+        })
+    }
+
+    /// Goes to a state from the undo history.
+    fn restore_state(&mut self, state: Option<S>) -> bool {
+        let state = match state {
+            Some(state) => state,
+            None => return false
+        };
+
+        if let Some(ref mut history) = self.history {
+            history.record(state.clone(), None);
+        }
+
+        self.apply_state(state);
+        self.save_on_change();
+        true
+    }
+
+    /// Rebuilds all windows for a new state.
+    fn apply_state(&mut self, new_state: S) {
         self.state = new_state;
+        self.rebuild();
+        self.sync_subscriptions();
+
+        if let Some(status) = self.state.exit_status() {
+            self.exit_status = Some(status);
+        }
+    }
+
+    fn rebuild(&mut self) {
+        let (root, children) = {
+            let state = &self.state;
+            let build = || (state.build(), state.windows());
+            match self.component_id_seed {
+                Some(seed) => Component::build_seeded(seed, build),
+                None => build()
+            }
+        };
+
+        let properties = self.state.window();
+        self.windows[0].set_tree(root);
+        self.windows[0].set_properties(properties);
+        self.sync_child_windows(children);
+    }
+
+    /// Starts the subscriptions the state asks for that aren't running yet, and stops the ones it
+    /// no longer asks for.
+    fn sync_subscriptions(&mut self) {
+        let now = Instant::now();
+        let wanted = self.state.subscriptions();
+
+        self.subscriptions.retain(|key, _| wanted.iter().any(|subscription| subscription.key() == key));
+        for subscription in wanted {
+            if !self.subscriptions.contains_key(subscription.key()) {
+                self.subscriptions.insert(subscription.key().to_string(), subscription.start(now));
+            }
+        }
+    }
+
+    /// Matches the child windows with the ones described by the state. Windows that are no longer
+    /// described are closed right away, new ones are opened by `sync_open_windows` while running.
+    fn sync_child_windows(&mut self, children: Vec<ChildWindow>) {
+        let keys: Vec<String> = children.iter().map(|child| child.key.clone()).collect();
+        self.dismissed_windows.retain(|key| keys.contains(key));
+
+        for index in 1..self.windows.len() {
+            let keep = match self.windows[index].key {
+                Some(ref key) => keys.contains(key) && !self.dismissed_windows.contains(key),
+                None => false
+            };
+
+            if !keep && !self.windows[index].closed {
+                self.close_window(index);
+            }
+        }
 
-        tree
+        for child in children {
+            if self.dismissed_windows.contains(&child.key) {
+                continue;
+            }
+
+            let existing = self.windows.iter().position(|window| !window.closed && window.key.as_ref() == Some(&child.key));
+            match existing {
+                Some(index) => {
+                    let window = &mut self.windows[index];
+                    window.set_tree(child.root);
+                    window.set_properties(child.properties);
+                },
+                None => {
+                    let window = AppWindow::new(Some(child.key), child.options, child.properties, child.root, &self.gesture_config);
+                    self.windows.push(window);
+                }
+            }
+        }
+    }
+
+    /// Closes a child window the user closed. It stays closed until the state stops describing it,
+    /// and its parent is told with a `ChildWindowClosed`.
+    fn close_child_window(&mut self, index: usize) {
+        if let Some(key) = self.windows[index].key.clone() {
+            self.close_window(index);
+            self.dismissed_windows.insert(key.clone());
+            self.dispatch(0, &[], |_| ChildWindowClosed { key });
+        }
+    }
+
+    fn close_window(&mut self, index: usize) {
+        if let Some(id) = self.windows[index].close() {
+            self.closed_window_ids.push(id);
+        }
+    }
+
+    /// Closed windows stay in place while an event is handled, so that indices remain valid.
+    fn purge_closed_windows(&mut self) {
+        self.windows.retain(|window| !window.closed);
+        // Buttons held in a closed window are never released
+        self.update_pointer_transaction();
+    }
+
+    fn sync_open_windows(&mut self, event_stream: &mut EventStream) {
+        for id in self.closed_window_ids.drain(..) {
+            event_stream.forget(&id);
+        }
+
+        for window in &mut self.windows {
+            window.open(event_stream);
+        }
     }
-}
\ No newline at end of file
+
+    /// Renders a frame for every window that changed since its last one. `run` does this once
+    /// all pending events have been handled; without a window this only lays out.
+    pub fn render(&mut self) {
+        for window in &mut self.windows {
+            window.render();
+        }
+    }
+
+    /// The number of frames the main window has rendered.
+    pub fn frames(&self) -> usize {
+        self.windows[0].frames
+    }
+}
@@ -0,0 +1,312 @@
+use webrender::api::{LayoutPoint, WorldPoint};
+use input::{local_point, Interaction, PointerEvent, TouchEvent, TouchPhase};
+use model::ComponentPath;
+use std::f32::consts::PI;
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Debug)]
+pub struct GestureConfig {
+    /// Maximum time between two clicks on the same component to count as a double-click.
+    pub double_click_interval: Duration,
+    /// Maximum distance the pointer may move between two clicks that count as a double-click.
+    pub double_click_distance: f32,
+    /// Time a press has to be held without dragging before it becomes a long-press.
+    pub long_press_duration: Duration,
+    /// Distance the pointer has to travel while pressed before a drag starts.
+    pub drag_threshold: f32,
+}
+
+impl Default for GestureConfig {
+    fn default() -> GestureConfig {
+        GestureConfig {
+            double_click_interval: Duration::from_millis(500),
+            double_click_distance: 4.0,
+            long_press_duration: Duration::from_millis(800),
+            drag_threshold: 4.0,
+        }
+    }
+}
+
+impl GestureConfig {
+    /// Whether two presses are close enough to be part of a double-click.
+    pub(crate) fn is_near(&self, a: &WorldPoint, b: &WorldPoint) -> bool {
+        (a.x - b.x).abs() <= self.double_click_distance && (a.y - b.y).abs() <= self.double_click_distance
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct DragEvent {
+    pub pointer: PointerEvent,
+    /// Window position at which the press that started the drag happened.
+    pub start_position: WorldPoint,
+}
+
+#[derive(Clone, Debug)]
+pub struct PinchEvent {
+    /// Distance between the two fingers relative to the distance when the second finger went down.
+    pub scale: f32,
+    pub window_center: WorldPoint,
+    pub local_center: LayoutPoint,
+}
+
+#[derive(Clone, Debug)]
+pub struct RotateEvent {
+    /// Clockwise rotation in radians since the second finger went down. Keeps growing past half a
+    /// turn.
+    pub rotation: f32,
+    pub window_center: WorldPoint,
+    pub local_center: LayoutPoint,
+}
+
+#[derive(Clone, Debug)]
+pub enum Gesture {
+    Click(PointerEvent),
+    DoubleClick(PointerEvent),
+    LongPress(PointerEvent),
+    DragStart(DragEvent),
+    DragMove(DragEvent),
+    DragEnd(DragEvent),
+    Pinch(PinchEvent),
+    Rotate(RotateEvent),
+}
+
+impl_describe_debug!(Gesture);
+
+impl Gesture {
+    /// Returns the gesture with its local position made relative to `origin`.
+    pub fn localize(mut self, origin: &LayoutPoint) -> Gesture {
+        match self {
+            Gesture::Click(ref mut e) | Gesture::DoubleClick(ref mut e) | Gesture::LongPress(ref mut e) => e.localize(origin),
+            Gesture::DragStart(ref mut e) | Gesture::DragMove(ref mut e) | Gesture::DragEnd(ref mut e) => e.pointer.localize(origin),
+            Gesture::Pinch(ref mut e) => e.local_center = local_point(&e.window_center, origin),
+            Gesture::Rotate(ref mut e) => e.local_center = local_point(&e.window_center, origin),
+        }
+        self
+    }
+}
+
+struct Press {
+    target: ComponentPath,
+    event: PointerEvent,
+    dragging: bool,
+    long_pressed: bool,
+}
+
+struct Touch {
+    id: u64,
+    target: Option<ComponentPath>,
+    position: WorldPoint,
+}
+
+struct TwoFingerGesture {
+    target: ComponentPath,
+    ids: (u64, u64),
+    start_distance: f32,
+    /// The angle between the fingers at the last move, and the rotation up to it. Rotations are
+    /// summed up move by move, so that they can go past half a turn.
+    last_angle: f32,
+    rotation: f32,
+}
+
+/// Turns raw `Interaction`s and `TouchEvent`s into `Gesture`s. Components are identified by their
+/// `ComponentPath`, since a press usually causes the tree to be rebuilt before the matching release
+/// arrives.
+pub struct GestureRecognizer {
+    config: GestureConfig,
+    press: Option<Press>,
+    last_click: Option<(ComponentPath, WorldPoint, Instant)>,
+    touches: Vec<Touch>,
+    two_finger: Option<TwoFingerGesture>,
+}
+
+impl GestureRecognizer {
+    pub fn new(config: GestureConfig) -> GestureRecognizer {
+        GestureRecognizer {
+            config,
+            press: None,
+            last_click: None,
+            touches: Vec::new(),
+            two_finger: None,
+        }
+    }
+
+    /// Feeds an interaction that was hit-tested to `target` and returns the recognized gestures
+    /// together with the component each of them should be dispatched to.
+    pub fn handle(&mut self, target: Option<&ComponentPath>, interaction: &Interaction) -> Vec<(ComponentPath, Gesture)> {
+        let mut gestures = Vec::new();
+
+        match *interaction {
+            Interaction::Pressed(ref e) => {
+                if self.press.is_none() {
+                    if let Some(target) = target {
+                        self.press = Some(Press {
+                            target: target.clone(),
+                            event: e.clone(),
+                            dragging: false,
+                            long_pressed: false,
+                        });
+                    }
+                }
+            },
+            Interaction::Moved(ref e) => {
+                if let Some(ref mut press) = self.press {
+                    let start = press.event.window_position;
+                    if press.dragging {
+                        gestures.push((press.target.clone(), Gesture::DragMove(DragEvent { pointer: e.clone(), start_position: start })));
+                    } else if distance(&start, &e.window_position) > self.config.drag_threshold {
+                        press.dragging = true;
+                        gestures.push((press.target.clone(), Gesture::DragStart(DragEvent { pointer: e.clone(), start_position: start })));
+                    }
+                }
+            },
+            Interaction::Released(ref e) => {
+                let is_pressed_button = self.press.as_ref().map_or(false, |press| press.event.button == e.button);
+                if is_pressed_button {
+                    let press = self.press.take().unwrap();
+                    if press.dragging {
+                        gestures.push((press.target, Gesture::DragEnd(DragEvent { pointer: e.clone(), start_position: press.event.window_position })));
+                    } else if press.long_pressed {
+                        // Already reported by tick()
+                    } else if e.timestamp.duration_since(press.event.timestamp) >= self.config.long_press_duration {
+                        gestures.push((press.target, Gesture::LongPress(press.event)));
+                    } else if target == Some(&press.target) {
+                        gestures.extend(self.click(press.target, &press.event, e));
+                    }
+                }
+            },
+            Interaction::Wheel(_) => {}
+        }
+
+        gestures
+    }
+
+    /// Feeds a touch that was hit-tested to `target` when it started. Pinches and rotations are
+    /// recognized for the first two fingers and dispatched to the target of the first one.
+    pub fn handle_touch(&mut self, target: Option<&ComponentPath>, touch: &TouchEvent) -> Vec<(ComponentPath, Gesture)> {
+        let mut gestures = Vec::new();
+
+        match touch.phase {
+            TouchPhase::Started => {
+                self.touches.push(Touch {
+                    id: touch.id,
+                    target: target.cloned(),
+                    position: touch.window_position,
+                });
+
+                if self.two_finger.is_none() && self.touches.len() == 2 {
+                    if let Some(ref target) = self.touches[0].target {
+                        let (a, b) = (&self.touches[0], &self.touches[1]);
+                        self.two_finger = Some(TwoFingerGesture {
+                            target: target.clone(),
+                            ids: (a.id, b.id),
+                            start_distance: distance(&a.position, &b.position),
+                            last_angle: angle(&a.position, &b.position),
+                            rotation: 0.0,
+                        });
+                    }
+                }
+            },
+            TouchPhase::Moved => {
+                if let Some(t) = self.touches.iter_mut().find(|t| t.id == touch.id) {
+                    t.position = touch.window_position;
+                }
+
+                if let Some(ref mut two_finger) = self.two_finger {
+                    let a = self.touches.iter().find(|t| t.id == two_finger.ids.0).map(|t| t.position);
+                    let b = self.touches.iter().find(|t| t.id == two_finger.ids.1).map(|t| t.position);
+                    if let (Some(a), Some(b)) = (a, b) {
+                        let center = WorldPoint::new((a.x + b.x) / 2.0, (a.y + b.y) / 2.0);
+                        let local_center = LayoutPoint::new(center.x, center.y);
+
+                        if two_finger.start_distance > 0.0 {
+                            gestures.push((two_finger.target.clone(), Gesture::Pinch(PinchEvent {
+                                scale: distance(&a, &b) / two_finger.start_distance,
+                                window_center: center,
+                                local_center,
+                            })));
+                        }
+                        let current_angle = angle(&a, &b);
+                        two_finger.rotation += wrap_angle(current_angle - two_finger.last_angle);
+                        two_finger.last_angle = current_angle;
+                        gestures.push((two_finger.target.clone(), Gesture::Rotate(RotateEvent {
+                            rotation: two_finger.rotation,
+                            window_center: center,
+                            local_center,
+                        })));
+                    }
+                }
+            },
+            TouchPhase::Ended | TouchPhase::Cancelled => {
+                self.touches.retain(|t| t.id != touch.id);
+
+                let ends_two_finger = self.two_finger.as_ref().map_or(false, |g| g.ids.0 == touch.id || g.ids.1 == touch.id);
+                if ends_two_finger {
+                    self.two_finger = None;
+                }
+            }
+        }
+
+        gestures
+    }
+
+    /// Reports a long-press as soon as its duration has passed, instead of waiting for the release.
+    pub fn tick(&mut self, now: Instant) -> Vec<(ComponentPath, Gesture)> {
+        let mut gestures = Vec::new();
+
+        if let Some(ref mut press) = self.press {
+            if !press.dragging && !press.long_pressed && now >= press.event.timestamp + self.config.long_press_duration {
+                press.long_pressed = true;
+                gestures.push((press.target.clone(), Gesture::LongPress(press.event.clone())));
+            }
+        }
+
+        gestures
+    }
+
+    /// The moment at which `tick` would report a long-press for the current press, if any.
+    pub fn next_deadline(&self) -> Option<Instant> {
+        match self.press {
+            Some(ref press) if !press.dragging && !press.long_pressed => Some(press.event.timestamp + self.config.long_press_duration),
+            _ => None
+        }
+    }
+
+    /// Presses are compared like `PointerState` does for click counts, so both agree on what a
+    /// double-click is.
+    fn click(&mut self, target: ComponentPath, press: &PointerEvent, e: &PointerEvent) -> Vec<(ComponentPath, Gesture)> {
+        let is_double_click = match self.last_click {
+            Some((ref last_target, last_position, last_time)) => {
+                *last_target == target &&
+                    press.timestamp.duration_since(last_time) <= self.config.double_click_interval &&
+                    self.config.is_near(&last_position, &press.window_position)
+            },
+            None => false
+        };
+
+        let mut gestures = vec![(target.clone(), Gesture::Click(e.clone()))];
+        if is_double_click {
+            self.last_click = None;
+            gestures.push((target, Gesture::DoubleClick(e.clone())));
+        } else {
+            self.last_click = Some((target, press.window_position, press.timestamp));
+        }
+
+        gestures
+    }
+}
+
+fn distance(a: &WorldPoint, b: &WorldPoint) -> f32 {
+    ((a.x - b.x).powi(2) + (a.y - b.y).powi(2)).sqrt()
+}
+
+fn angle(a: &WorldPoint, b: &WorldPoint) -> f32 {
+    (b.y - a.y).atan2(b.x - a.x)
+}
+
+/// Brings a difference of two angles into (-π, π], so that crossing the ±π boundary of `atan2` is
+/// a small step and not almost a full turn.
+fn wrap_angle(angle: f32) -> f32 {
+    let turns = ((angle + PI) / (2.0 * PI)).floor();
+    let wrapped = angle - turns * 2.0 * PI;
+    if wrapped == -PI { PI } else { wrapped }
+}
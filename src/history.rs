@@ -0,0 +1,134 @@
+use model::{ComponentId, ComponentPath, Event, InspectableComponent};
+use std::any;
+use std::fmt::Debug;
+use std::io::{self, Write};
+use std::time::Instant;
+
+/// The component that handled the event which caused a state transition.
+#[derive(Clone, Debug)]
+pub struct ComponentInfo {
+    pub id: ComponentId,
+    pub name: Option<String>,
+    /// Path of the component in the tree of its window.
+    pub path: ComponentPath,
+}
+
+impl ComponentInfo {
+    pub fn new(component: &InspectableComponent, path: &[usize]) -> ComponentInfo {
+        ComponentInfo {
+            id: *component.id(),
+            name: component.name().clone(),
+            path: path.to_vec(),
+        }
+    }
+}
+
+/// Describes what caused a state transition.
+#[derive(Clone, Debug)]
+pub struct Cause {
+    /// The type name of the event, e.g. `weld::gesture::Gesture`.
+    pub event_type: &'static str,
+    /// The description of the event, see `Event::describe`, or its type name when it has none.
+    pub event: String,
+    /// The component that handled the event. Events that don't come from the component tree, such
+    /// as subscription items, have none.
+    pub component: Option<ComponentInfo>,
+}
+
+impl Cause {
+    pub fn new<E: Event>(event: &E, component: Option<ComponentInfo>) -> Cause {
+        Cause {
+            event_type: any::type_name::<E>(),
+            event: event.describe().unwrap_or_else(|| any::type_name::<E>().to_string()),
+            component,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct HistoryEntry<S> {
+    pub state: S,
+    /// None for the initial state and for transitions that weren't caused by an event, such as
+    /// subscription items and updates posted through a `Dispatcher`.
+    pub cause: Option<Cause>,
+    pub timestamp: Instant,
+}
+
+/// Every state an application went through, for stepping back and forth between them while
+/// debugging. Moving away from the latest state shows an earlier one; a transition that happens
+/// while an earlier state is shown discards the states after it.
+pub struct History<S> {
+    entries: Vec<HistoryEntry<S>>,
+    position: usize,
+}
+
+impl<S: Clone> History<S> {
+    pub fn new(initial: S) -> History<S> {
+        History {
+            entries: vec![HistoryEntry {
+                state: initial,
+                cause: None,
+                timestamp: Instant::now(),
+            }],
+            position: 0,
+        }
+    }
+
+    pub fn entries(&self) -> &[HistoryEntry<S>] {
+        &self.entries
+    }
+
+    /// Index of the entry whose state is shown.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn record(&mut self, state: S, cause: Option<Cause>) {
+        self.entries.truncate(self.position + 1);
+        self.entries.push(HistoryEntry {
+            state,
+            cause,
+            timestamp: Instant::now(),
+        });
+        self.position = self.entries.len() - 1;
+    }
+
+    /// Moves to the entry at `position` and returns its state, or None when there is no such entry.
+    pub fn jump_to(&mut self, position: usize) -> Option<S> {
+        if position >= self.entries.len() {
+            return None;
+        }
+
+        self.position = position;
+        Some(self.entries[position].state.clone())
+    }
+
+    /// Writes every entry with its cause and state in a readable form.
+    pub fn export<W: Write>(&self, writer: &mut W) -> io::Result<()> where S: Debug {
+        let start = self.entries[0].timestamp;
+
+        for (index, entry) in self.entries.iter().enumerate() {
+            let elapsed = entry.timestamp.duration_since(start);
+            let millis = elapsed.as_secs() * 1000 + (elapsed.subsec_nanos() / 1_000_000) as u64;
+            let marker = if index == self.position { "*" } else { " " };
+
+            match entry.cause {
+                Some(ref cause) => {
+                    write!(writer, "{}#{} +{}ms {}", marker, index, millis, cause.event_type)?;
+                    if let Some(ref component) = cause.component {
+                        write!(writer, " on {:?}", component.path)?;
+                        if let Some(ref name) = component.name {
+                            write!(writer, " \"{}\"", name)?;
+                        }
+                    }
+                    writeln!(writer)?;
+                    writeln!(writer, "    event: {}", cause.event)?;
+                },
+                None => writeln!(writer, "{}#{} +{}ms", marker, index, millis)?
+            }
+            writeln!(writer, "    state: {:?}", entry.state)?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,271 @@
+use glutin;
+use webrender::api::{LayoutPoint, WorldPoint};
+use gesture::GestureConfig;
+use window;
+use std::time::Instant;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MouseButton {
+    Left,
+    Right,
+    Middle,
+    Other(u8),
+}
+
+impl From<glutin::MouseButton> for MouseButton {
+    fn from(button: glutin::MouseButton) -> MouseButton {
+        match button {
+            glutin::MouseButton::Left => MouseButton::Left,
+            glutin::MouseButton::Right => MouseButton::Right,
+            glutin::MouseButton::Middle => MouseButton::Middle,
+            glutin::MouseButton::Other(n) => MouseButton::Other(n),
+        }
+    }
+}
+
+/// Modifier keys held down while an event was generated. `logo` is the Super/Windows/Command key.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Modifiers {
+    pub ctrl: bool,
+    pub shift: bool,
+    pub alt: bool,
+    pub logo: bool,
+}
+
+impl Modifiers {
+    pub fn none() -> Modifiers {
+        Modifiers::default()
+    }
+
+    /// Tracks the modifier state from a keyboard event. Glutin reports the modifiers as they were
+    /// before the key changed state, so presses of the modifier keys themselves are applied on top.
+    pub fn update(&mut self, input: &glutin::KeyboardInput) {
+        *self = input.modifiers.into();
+
+        let pressed = input.state == glutin::ElementState::Pressed;
+        match input.virtual_keycode {
+            Some(glutin::VirtualKeyCode::LControl) | Some(glutin::VirtualKeyCode::RControl) => self.ctrl = pressed,
+            Some(glutin::VirtualKeyCode::LShift) | Some(glutin::VirtualKeyCode::RShift) => self.shift = pressed,
+            Some(glutin::VirtualKeyCode::LAlt) | Some(glutin::VirtualKeyCode::RAlt) => self.alt = pressed,
+            Some(glutin::VirtualKeyCode::LWin) | Some(glutin::VirtualKeyCode::RWin) => self.logo = pressed,
+            _ => {}
+        }
+    }
+}
+
+impl From<glutin::ModifiersState> for Modifiers {
+    fn from(state: glutin::ModifiersState) -> Modifiers {
+        Modifiers {
+            ctrl: state.ctrl,
+            shift: state.shift,
+            alt: state.alt,
+            logo: state.logo,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct PointerEvent {
+    /// The button that changed state. For moves this is the first button that is still held down, if any.
+    pub button: Option<MouseButton>,
+    /// Number of consecutive presses of the same button, e.g. 2 for the second press of a double-click.
+    pub click_count: u32,
+    pub modifiers: Modifiers,
+    pub window_position: WorldPoint,
+    /// Position relative to the top-left corner of the component that receives the event.
+    pub local_position: LayoutPoint,
+    pub timestamp: Instant,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WheelDelta {
+    Lines(f32, f32),
+    Pixels(f32, f32),
+}
+
+impl From<glutin::MouseScrollDelta> for WheelDelta {
+    fn from(delta: glutin::MouseScrollDelta) -> WheelDelta {
+        match delta {
+            glutin::MouseScrollDelta::LineDelta(x, y) => WheelDelta::Lines(x, y),
+            glutin::MouseScrollDelta::PixelDelta(x, y) => WheelDelta::Pixels(x, y),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct WheelEvent {
+    pub delta: WheelDelta,
+    pub modifiers: Modifiers,
+    pub window_position: WorldPoint,
+    pub local_position: LayoutPoint,
+    pub timestamp: Instant,
+}
+
+#[derive(Clone, Debug)]
+pub enum Interaction {
+    Pressed(PointerEvent),
+    Released(PointerEvent),
+    Moved(PointerEvent),
+    Wheel(WheelEvent),
+}
+
+impl_describe_debug!(Interaction);
+
+impl Interaction {
+    pub fn window_position(&self) -> WorldPoint {
+        match *self {
+            Interaction::Pressed(ref e) | Interaction::Released(ref e) | Interaction::Moved(ref e) => e.window_position,
+            Interaction::Wheel(ref e) => e.window_position,
+        }
+    }
+
+    /// Returns the interaction with its local position made relative to `origin`.
+    pub fn localize(mut self, origin: &LayoutPoint) -> Interaction {
+        match self {
+            Interaction::Pressed(ref mut e) | Interaction::Released(ref mut e) | Interaction::Moved(ref mut e) => e.localize(origin),
+            Interaction::Wheel(ref mut e) => e.local_position = local_point(&e.window_position, origin),
+        }
+        self
+    }
+}
+
+impl PointerEvent {
+    pub fn localize(&mut self, origin: &LayoutPoint) {
+        self.local_position = local_point(&self.window_position, origin);
+    }
+}
+
+pub(crate) fn local_point(window_position: &WorldPoint, origin: &LayoutPoint) -> LayoutPoint {
+    LayoutPoint::new(window_position.x - origin.x, window_position.y - origin.y)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TouchPhase {
+    Started,
+    Moved,
+    Ended,
+    Cancelled,
+}
+
+impl From<glutin::TouchPhase> for TouchPhase {
+    fn from(phase: glutin::TouchPhase) -> TouchPhase {
+        match phase {
+            glutin::TouchPhase::Started => TouchPhase::Started,
+            glutin::TouchPhase::Moved => TouchPhase::Moved,
+            glutin::TouchPhase::Ended => TouchPhase::Ended,
+            glutin::TouchPhase::Cancelled => TouchPhase::Cancelled,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct TouchEvent {
+    /// Identifies the finger for as long as it touches the screen.
+    pub id: u64,
+    pub phase: TouchPhase,
+    pub window_position: WorldPoint,
+    pub local_position: LayoutPoint,
+    pub timestamp: Instant,
+}
+
+impl_describe_debug!(TouchEvent);
+
+impl TouchEvent {
+    pub fn localize(&mut self, origin: &LayoutPoint) {
+        self.local_position = local_point(&self.window_position, origin);
+    }
+}
+
+impl TouchEvent {
+    /// Converts a glutin touch, whose location is in device pixels.
+    pub fn from_glutin(touch: glutin::Touch, device_pixel_ratio: f32) -> TouchEvent {
+        let position = window::logical_point(touch.location, device_pixel_ratio);
+
+        TouchEvent {
+            id: touch.id,
+            phase: touch.phase.into(),
+            window_position: position,
+            local_position: LayoutPoint::new(position.x, position.y),
+            timestamp: Instant::now(),
+        }
+    }
+}
+
+/// Keeps track of the pointer between glutin events, so that every `Interaction` can carry the
+/// full pointer state. Presses count as repeated clicks with the same thresholds the gesture
+/// recognizer uses for double-clicks.
+pub struct PointerState {
+    pub position: WorldPoint,
+    pub modifiers: Modifiers,
+    config: GestureConfig,
+    pressed: Vec<MouseButton>,
+    last_press: Option<(MouseButton, WorldPoint, Instant)>,
+    click_count: u32,
+}
+
+impl PointerState {
+    pub fn new(config: GestureConfig) -> PointerState {
+        PointerState {
+            config,
+            position: WorldPoint::zero(),
+            modifiers: Modifiers::none(),
+            pressed: Vec::new(),
+            last_press: None,
+            click_count: 0,
+        }
+    }
+
+    pub fn moved(&mut self, position: WorldPoint) -> Interaction {
+        self.position = position;
+        let button = self.pressed.first().cloned();
+        let mut event = self.pointer_event(button);
+        event.click_count = 0;
+        Interaction::Moved(event)
+    }
+
+    pub fn pressed(&mut self, button: MouseButton) -> Interaction {
+        let now = Instant::now();
+        let is_repeat = match self.last_press {
+            Some((last_button, last_position, last_time)) => {
+                last_button == button &&
+                    now.duration_since(last_time) <= self.config.double_click_interval &&
+                    self.config.is_near(&last_position, &self.position)
+            },
+            None => false
+        };
+
+        self.click_count = if is_repeat { self.click_count + 1 } else { 1 };
+        self.last_press = Some((button, self.position, now));
+        if !self.pressed.contains(&button) {
+            self.pressed.push(button);
+        }
+
+        Interaction::Pressed(self.pointer_event(Some(button)))
+    }
+
+    pub fn released(&mut self, button: MouseButton) -> Interaction {
+        self.pressed.retain(|b| *b != button);
+        Interaction::Released(self.pointer_event(Some(button)))
+    }
+
+    pub fn wheel(&mut self, delta: WheelDelta) -> Interaction {
+        Interaction::Wheel(WheelEvent {
+            delta,
+            modifiers: self.modifiers,
+            window_position: self.position,
+            local_position: LayoutPoint::new(self.position.x, self.position.y),
+            timestamp: Instant::now(),
+        })
+    }
+
+    fn pointer_event(&self, button: Option<MouseButton>) -> PointerEvent {
+        PointerEvent {
+            button,
+            click_count: self.click_count,
+            modifiers: self.modifiers,
+            window_position: self.position,
+            local_position: LayoutPoint::new(self.position.x, self.position.y),
+            timestamp: Instant::now(),
+        }
+    }
+}
@@ -0,0 +1,204 @@
+use layout::{FlexStyle, StyleUnit};
+use layout_context::LayoutContext;
+use model::{Component, InspectableComponent, RenderElement};
+use webrender::api::{ColorF, LayoutPoint, LayoutRect, LayoutSize};
+use std::fmt::Write;
+
+const PANEL_WIDTH: f32 = 240.0;
+const ROW_HEIGHT: f32 = 10.0;
+const INDENT: f32 = 12.0;
+
+/// Distances on each side of a box, in logical pixels.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Edges {
+    pub left: f32,
+    pub top: f32,
+    pub right: f32,
+    pub bottom: f32,
+}
+
+/// The box model of a component: its margins, its layout box and its padding.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BoxModel {
+    pub bounds: LayoutRect,
+    pub margin: Edges,
+    pub padding: Edges,
+}
+
+impl BoxModel {
+    /// Margins and padding are taken from the styles of the component, including the ones set by
+    /// running transitions. Only point and percent values are known, other values count as 0.
+    pub fn new(component: &InspectableComponent, parent_width: f32, layout_context: &LayoutContext) -> BoxModel {
+        let mut styles: Vec<&FlexStyle> = component.styles().iter().collect();
+        if let Some(overrides) = layout_context.style_overrides(component.id()) {
+            styles.extend(overrides.iter());
+        }
+
+        BoxModel {
+            bounds: layout_context.absolute_bounds(component),
+            margin: edges(&styles, true, parent_width),
+            padding: edges(&styles, false, parent_width),
+        }
+    }
+
+    pub fn margin_box(&self) -> LayoutRect {
+        outset(&self.bounds, &self.margin)
+    }
+
+    pub fn content_box(&self) -> LayoutRect {
+        let inverted = Edges {
+            left: -self.padding.left,
+            top: -self.padding.top,
+            right: -self.padding.right,
+            bottom: -self.padding.bottom,
+        };
+        outset(&self.bounds, &inverted)
+    }
+}
+
+fn outset(rect: &LayoutRect, edges: &Edges) -> LayoutRect {
+    LayoutRect::new(
+        LayoutPoint::new(rect.origin.x - edges.left, rect.origin.y - edges.top),
+        LayoutSize::new(
+            (rect.size.width + edges.left + edges.right).max(0.0),
+            (rect.size.height + edges.top + edges.bottom).max(0.0)
+        )
+    )
+}
+
+/// Later styles override earlier ones, like they do in the layout.
+fn edges(styles: &[&FlexStyle], margin: bool, parent_width: f32) -> Edges {
+    let mut edges = Edges::default();
+    let value = |unit: &StyleUnit| match *unit {
+        StyleUnit::Point(value) => value.into_inner(),
+        // Percentages of margins and padding are relative to the width of the parent on every side
+        StyleUnit::Percent(value) => value.into_inner() / 100.0 * parent_width,
+        _ => 0.0
+    };
+
+    for style in styles {
+        match (*style, margin) {
+            (&FlexStyle::Margin(ref unit), true) | (&FlexStyle::Padding(ref unit), false) => {
+                let v = value(unit);
+                edges = Edges { left: v, top: v, right: v, bottom: v };
+            },
+            (&FlexStyle::MarginHorizontal(ref unit), true) | (&FlexStyle::PaddingHorizontal(ref unit), false) => {
+                edges.left = value(unit);
+                edges.right = value(unit);
+            },
+            (&FlexStyle::MarginVertical(ref unit), true) | (&FlexStyle::PaddingVertical(ref unit), false) => {
+                edges.top = value(unit);
+                edges.bottom = value(unit);
+            },
+            (&FlexStyle::MarginLeft(ref unit), true) | (&FlexStyle::PaddingLeft(ref unit), false) => edges.left = value(unit),
+            (&FlexStyle::MarginTop(ref unit), true) | (&FlexStyle::PaddingTop(ref unit), false) => edges.top = value(unit),
+            (&FlexStyle::MarginRight(ref unit), true) | (&FlexStyle::PaddingRight(ref unit), false) => edges.right = value(unit),
+            (&FlexStyle::MarginBottom(ref unit), true) | (&FlexStyle::PaddingBottom(ref unit), false) => edges.bottom = value(unit),
+            _ => {}
+        }
+    }
+
+    edges
+}
+
+/// The rectangles the inspector draws on top of a window: the box model of the hovered component,
+/// and a panel along the right edge with a row for every component of the tree, indented by depth.
+/// The row of the hovered component is highlighted.
+///
+/// The renderer only draws rectangles, so names, styles and the state can't be shown in the
+/// window; `describe` writes them as text, which the application logs on request.
+pub fn overlay(root: &Component, layout_context: &LayoutContext, hovered: Option<&[usize]>, window_size: &LayoutSize) -> Vec<RenderElement> {
+    let mut elements = Vec::new();
+
+    if let Some(path) = hovered {
+        if let Some(box_model) = find_box_model(root, path, layout_context, window_size.width) {
+            elements.push(RenderElement::Rect(box_model.margin_box(), ColorF::new(0.98, 0.6, 0.2, 0.35)));
+            elements.push(RenderElement::Rect(box_model.bounds, ColorF::new(0.4, 0.8, 0.4, 0.35)));
+            elements.push(RenderElement::Rect(box_model.content_box(), ColorF::new(0.3, 0.6, 0.95, 0.35)));
+        }
+    }
+
+    let panel_width = PANEL_WIDTH.min(window_size.width / 3.0);
+    let panel = LayoutRect::new(
+        LayoutPoint::new(window_size.width - panel_width, 0.0),
+        LayoutSize::new(panel_width, window_size.height)
+    );
+    elements.push(RenderElement::Rect(panel, ColorF::new(0.1, 0.1, 0.12, 0.85)));
+
+    let mut row = 0;
+    add_rows(&mut elements, root, &mut Vec::new(), hovered, &panel, &mut row);
+
+    elements
+}
+
+fn add_rows(elements: &mut Vec<RenderElement>, node: &Component, path: &mut Vec<usize>, hovered: Option<&[usize]>, panel: &LayoutRect, row: &mut usize) {
+    let top = panel.origin.y + 4.0 + *row as f32 * ROW_HEIGHT;
+    if top + ROW_HEIGHT > panel.origin.y + panel.size.height {
+        return;
+    }
+
+    let left = panel.origin.x + 4.0 + path.len() as f32 * INDENT;
+    let width = (panel.origin.x + panel.size.width - 4.0 - left).max(INDENT);
+    let color = if hovered == Some(&path[..]) {
+        ColorF::new(0.3, 0.6, 0.95, 1.0)
+    } else {
+        ColorF::new(0.6, 0.6, 0.65, 1.0)
+    };
+    elements.push(RenderElement::Rect(LayoutRect::new(LayoutPoint::new(left, top), LayoutSize::new(width, ROW_HEIGHT - 4.0)), color));
+    *row += 1;
+
+    for (index, child) in node.inspect().children().iter().enumerate() {
+        path.push(index);
+        add_rows(elements, child, path, hovered, panel, row);
+        path.pop();
+    }
+}
+
+fn find_box_model(root: &Component, path: &[usize], layout_context: &LayoutContext, window_width: f32) -> Option<BoxModel> {
+    let component = root.find_by_path(path)?;
+    let parent_width = match path.split_last() {
+        Some((_, parent_path)) => layout_context.get_bounds(root.find_by_path(parent_path)?.inspect()).size.width,
+        None => window_width
+    };
+    Some(BoxModel::new(component.inspect(), parent_width, layout_context))
+}
+
+/// Describes the tree with the names and renderer ids of its components, followed by the box
+/// model, styles and handled event types of the hovered component. The hovered component is
+/// marked with `>`.
+pub fn describe(root: &Component, layout_context: &LayoutContext, hovered: Option<&[usize]>, window_size: &LayoutSize) -> String {
+    let mut output = String::new();
+    describe_tree(&mut output, root, &mut Vec::new(), hovered);
+
+    let hovered = hovered.and_then(|path| {
+        let component = root.find_by_path(path)?;
+        Some((path, component, find_box_model(root, path, layout_context, window_size.width)?))
+    });
+    if let Some((path, component, box_model)) = hovered {
+        let bounds = box_model.bounds;
+        writeln!(output).unwrap();
+        writeln!(output, "{:?} {}", path, component.inspect().renderer().id()).unwrap();
+        writeln!(output, "  bounds: ({}, {}) {}x{}", bounds.origin.x, bounds.origin.y, bounds.size.width, bounds.size.height).unwrap();
+        writeln!(output, "  margin: {:?}", box_model.margin).unwrap();
+        writeln!(output, "  padding: {:?}", box_model.padding).unwrap();
+        writeln!(output, "  styles: {:?}", component.inspect().styles()).unwrap();
+        writeln!(output, "  events: [{}]", component.inspect().event_types().join(", ")).unwrap();
+    }
+
+    output
+}
+
+fn describe_tree(output: &mut String, node: &Component, path: &mut Vec<usize>, hovered: Option<&[usize]>) {
+    let marker = if hovered == Some(&path[..]) { ">" } else { " " };
+    write!(output, "{} {}{}", marker, "  ".repeat(path.len()), node.inspect().renderer().id()).unwrap();
+    if let Some(ref name) = *node.inspect().name() {
+        write!(output, " {:?}", name).unwrap();
+    }
+    output.push('\n');
+
+    for (index, child) in node.inspect().children().iter().enumerate() {
+        path.push(index);
+        describe_tree(output, child, path, hovered);
+        path.pop();
+    }
+}
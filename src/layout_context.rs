@@ -1,36 +1,106 @@
 use webrender::api::*;
-use model::{Component, ComponentId, InspectableComponent, RenderContext, RenderElement};
+use model::{Component, ComponentId, ComponentPath, InspectableComponent, RenderContext, RenderElement};
 use std::collections::HashMap;
 use std::cell::{Ref, RefMut, RefCell};
 use layout;
+use layout::FlexStyle;
 
 pub struct LayoutContext {
-    layout_nodes: HashMap<ComponentId, RefCell<layout::Node>>
+    layout_nodes: HashMap<ComponentId, RefCell<layout::Node>>,
+    absolute_bounds: HashMap<ComponentId, LayoutRect>,
+    style_overrides: HashMap<ComponentId, Vec<FlexStyle>>,
+    color_overrides: HashMap<ComponentId, ColorF>,
+    overlay: Vec<RenderElement>,
+    layout_passes: usize,
 }
 
 impl LayoutContext {
     pub fn new() -> LayoutContext {
         LayoutContext {
-            layout_nodes: HashMap::new()
+            layout_nodes: HashMap::new(),
+            absolute_bounds: HashMap::new(),
+            style_overrides: HashMap::new(),
+            color_overrides: HashMap::new(),
+            overlay: Vec::new(),
+            layout_passes: 0,
         }
     }
 
+    /// Styles that are applied on top of the styles of the components with the given ids, e.g. by
+    /// running transitions. They take effect on the next `update_layout`.
+    pub fn set_style_overrides(&mut self, overrides: HashMap<ComponentId, Vec<FlexStyle>>) {
+        self.style_overrides = overrides;
+    }
+
+    pub fn style_overrides(&self, id: &ComponentId) -> Option<&Vec<FlexStyle>> {
+        self.style_overrides.get(id)
+    }
+
+    /// Elements that are drawn on top of the tree, e.g. by the inspector.
+    pub fn set_overlay(&mut self, overlay: Vec<RenderElement>) {
+        self.overlay = overlay;
+    }
+
+    /// Colors that replace the colors of the rectangles the components with the given ids render.
+    pub fn set_color_overrides(&mut self, overrides: HashMap<ComponentId, ColorF>) {
+        self.color_overrides = overrides;
+    }
+
     pub fn get_layout(&self, node: &InspectableComponent) -> layout::Layout {
         self.get_layout_node(node).get_layout()
     }
 
+    /// The bounds of a component relative to its parent.
+    pub fn get_bounds(&self, node: &InspectableComponent) -> LayoutRect {
+        let layout = self.get_layout(node);
+
+        LayoutRect::new(
+            LayoutPoint::new(layout.left, layout.top),
+            LayoutSize::new(layout.width, layout.height)
+        )
+    }
+
+    /// The bounds of a component relative to the window.
+    pub fn absolute_bounds(&self, node: &InspectableComponent) -> LayoutRect {
+        *self.absolute_bounds.get(node.id()).unwrap()
+    }
+
+    /// How often `update_layout` has laid out a tree.
+    pub fn layout_passes(&self) -> usize {
+        self.layout_passes
+    }
+
     pub fn update_layout(&mut self, root: &Component, size: &LayoutSize) {
+        self.layout_passes += 1;
+
         // HACK: Throw away old layout_nodes first
         self.layout_nodes = HashMap::new();
 
         self.update_layout_recursive(root);
         self.get_layout_node_mut(root).calculate_layout(size.width, size.height, layout::Direction::LTR);
+
+        self.absolute_bounds = HashMap::new();
+        self.update_absolute_bounds(root, &LayoutPoint::zero());
+    }
+
+    /// Yoga positions every node relative to its parent, so the offsets of all ancestors add up.
+    fn update_absolute_bounds(&mut self, node: &Component, parent_origin: &LayoutPoint) {
+        let bounds = self.get_bounds(node.inspect());
+        let origin = LayoutPoint::new(parent_origin.x + bounds.origin.x, parent_origin.y + bounds.origin.y);
+        self.absolute_bounds.insert(*node.inspect().id(), LayoutRect::new(origin, bounds.size));
+
+        for child in node.inspect().children() {
+            self.update_absolute_bounds(child, &origin);
+        }
     }
 
     fn update_layout_recursive(&mut self, node: &Component) {
         {
             let layout_node = self.layout_nodes.entry(*node.inspect().id()).or_insert_with(|| RefCell::new(layout::Node::new()));
             layout_node.borrow_mut().apply_styles(node.inspect().styles());
+            if let Some(overrides) = self.style_overrides.get(node.inspect().id()) {
+                layout_node.borrow_mut().apply_styles(overrides);
+            }
         }
 
         for child in node.inspect().children() {
@@ -43,17 +113,19 @@ impl LayoutContext {
         }
     }
 
-    pub fn build_display_list(&self, builder: &mut DisplayListBuilder, root: &Component) {
+    /// Layout happens in logical pixels, so every element is scaled by `device_pixel_ratio` to
+    /// end up in the device pixels the builder works with.
+    pub fn build_display_list(&self, builder: &mut DisplayListBuilder, root: &Component, device_pixel_ratio: f32) {
         let mut elements = Vec::new();
         {
             let mut ctx = WebrenderRenderContext::new(&self, root, &mut elements);
             root.inspect().renderer().render(&mut ctx);
         }
 
-        for element in elements {
-            match element {
+        for element in elements.iter().chain(self.overlay.iter()) {
+            match *element {
                 RenderElement::Rect(rect, color) => {
-                    builder.push_rect(rect, None, color);
+                    builder.push_rect(to_device_rect(&rect, device_pixel_ratio), None, color);
                 }
             }
         }
@@ -68,26 +140,44 @@ impl LayoutContext {
     }
 
     pub fn find_node_at<'a>(&self, point: WorldPoint, root: &'a Component) -> Option<&'a Component> {
-        self.find_node_at_recursive(point, root)
+        self.find_path_at(point, root).and_then(|path| root.find_by_path(&path))
     }
 
-    fn find_node_at_recursive<'a>(&self, point: WorldPoint, node: &'a Component) -> Option<&'a Component> {
-        let layout = self.get_layout(node);
-        let rect = WorldRect::new(WorldPoint::new(layout.left, layout.top), WorldSize::new(layout.width, layout.height));
-        if !rect.contains(&point) {
-            None
+    pub fn find_path_at(&self, point: WorldPoint, root: &Component) -> Option<ComponentPath> {
+        let mut path = Vec::new();
+        if self.find_path_at_recursive(&LayoutPoint::new(point.x, point.y), root, &mut path) {
+            Some(path)
         } else {
-            for child_id in node.inspect().children() {
-                if let Some(found_in_child) = self.find_node_at_recursive(point, child_id) {
-                    return Some(found_in_child);
-                }
-            }
+            None
+        }
+    }
 
-            Some(node)
+    fn find_path_at_recursive(&self, point: &LayoutPoint, node: &Component, path: &mut ComponentPath) -> bool {
+        let bounds = self.absolute_bounds(node.inspect());
+        if !bounds.contains(point) {
+            return false;
         }
+
+        for (index, child) in node.inspect().children().iter().enumerate() {
+            path.push(index);
+            if self.find_path_at_recursive(point, child, path) {
+                return true;
+            }
+            path.pop();
+        }
+
+        true
     }
 }
 
+/// Converts a rectangle in logical pixels to device pixels.
+pub fn to_device_rect(rect: &LayoutRect, device_pixel_ratio: f32) -> LayoutRect {
+    LayoutRect::new(
+        LayoutPoint::new(rect.origin.x * device_pixel_ratio, rect.origin.y * device_pixel_ratio),
+        LayoutSize::new(rect.size.width * device_pixel_ratio, rect.size.height * device_pixel_ratio)
+    )
+}
+
 struct WebrenderRenderContext<'a> {
     layout_context: &'a LayoutContext,
     component: &'a InspectableComponent,
@@ -110,6 +200,10 @@ impl<'a> RenderContext for WebrenderRenderContext<'a> {
     }
 
     fn push(&mut self, e: RenderElement) {
+        let e = match (e, self.layout_context.color_overrides.get(self.component.id())) {
+            (RenderElement::Rect(rect, _), Some(color)) => RenderElement::Rect(rect, *color),
+            (e, None) => e
+        };
         self.elements.push(e);
     }
 
@@ -121,11 +215,7 @@ impl<'a> RenderContext for WebrenderRenderContext<'a> {
     }
 
     fn bounds(&self) -> LayoutRect {
-        let layout = &self.layout_context.get_layout(self.component);
-
-        LayoutRect::new(
-            LayoutPoint::new(layout.left, layout.top),
-            LayoutSize::new(layout.width, layout.height)
-        )
+        // Everything ends up in a single display list, so elements are placed in the window
+        self.layout_context.absolute_bounds(self.component)
     }
 }
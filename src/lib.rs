@@ -3,7 +3,6 @@ extern crate gleam;
 extern crate glutin;
 #[macro_use]
 extern crate log;
-extern crate snowflake;
 extern crate webrender;
 extern crate rand;
 extern crate tokio_core;
@@ -11,9 +10,36 @@ extern crate futures;
 
 pub extern crate yoga;
 
+/// Implements `model::Event` for types that describe themselves with `Debug`.
+#[macro_export]
+macro_rules! impl_describe_debug {
+    ($($event:ty),*) => {
+        $(
+            impl $crate::model::Event for $event {
+                fn describe(&self) -> Option<String> {
+                    Some(format!("{:?}", self))
+                }
+            }
+        )*
+    }
+}
+
+pub mod animation;
 pub mod application;
+pub mod gesture;
+pub mod history;
+pub mod input;
+pub mod inspector;
 pub mod layout_context;
+pub mod middleware;
 pub mod model;
+pub mod persistence;
+pub mod recording;
+pub mod remote;
+pub mod snapshot;
+pub mod subscription;
+pub mod transition;
+pub mod undo;
 pub mod window;
 
 pub use yoga as layout;
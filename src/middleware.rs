@@ -0,0 +1,14 @@
+use history::Cause;
+use model::InvocationError;
+
+/// What a middleware gets to see of a state transition, next to the proposed new state.
+pub struct StateChange<'a, S: 'a> {
+    pub previous: &'a S,
+    /// The event that caused the transition and the component that handled it, if any.
+    pub cause: Option<&'a Cause>,
+}
+
+/// Sees every state transition before it is applied. Returns the new state, possibly modified, or
+/// an error to reject the transition altogether. States restored from the undo stack, the history
+/// or a saved session are not new and don't pass through middlewares.
+pub type Middleware<S> = Box<FnMut(&StateChange<S>, S) -> Result<S, InvocationError>>;
@@ -1,19 +1,72 @@
 use std::collections::HashMap;
-use std::any::{Any, TypeId};
+use std::any::{self, Any, TypeId};
 use std::result::Result;
 use std::marker::PhantomData;
 use std::borrow::Borrow;
+use std::cell::Cell;
 use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use layout::FlexStyle;
-use snowflake::ProcessUniqueId;
 use webrender::api::{LayoutRect, ColorF};
+use subscription::Subscription;
+use transition::Transition;
+use undo::UndoStep;
+use window::{ChildWindow, WindowProperties};
 
 ////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
 
-pub trait Event where Self: Sized + 'static {}
+pub trait Event where Self: Sized + 'static {
+    /// Describes the event for the state history and middleware, see `history::Cause`. Events
+    /// that don't describe themselves are only known by their type name there, so override this,
+    /// e.g. with `impl_describe_debug!` for events that implement `Debug`.
+    fn describe(&self) -> Option<String> {
+        None
+    }
+}
 
 pub trait State where Self: Clone + 'static {
     fn build(&self) -> Component;
+
+    /// Describes the window next to the component tree. After every state update, the properties
+    /// the window doesn't have are applied to it, including ones the user changed by hand since.
+    /// A size the user should be able to change has to follow `WindowResized` in the state, or be
+    /// left unset.
+    fn window(&self) -> WindowProperties {
+        WindowProperties::default()
+    }
+
+    /// Describes the windows to show next to the main window, each with its own component tree.
+    fn windows(&self) -> Vec<ChildWindow> {
+        Vec::new()
+    }
+
+    /// Whether the state is animating. As long as it is, an `AnimationFrame` is sent to the root
+    /// components on every frame.
+    fn animating(&self) -> bool {
+        false
+    }
+
+    /// The timers and streams the state wants to hear from. They are compared by key after every
+    /// state update, starting the new ones and stopping the ones that are gone.
+    fn subscriptions(&self) -> Vec<Subscription<Self>> {
+        Vec::new()
+    }
+
+    /// Makes the application quit with the returned status as soon as the state returns one, e.g.
+    /// after a `CloseRequested` was handled.
+    ///
+    /// With `Application::persistence`, the state that was saved when quitting is restored without
+    /// quitting, but the first transition from it quits again if it still returns a status. Leave
+    /// whatever makes it quit out of the saved state, or reset it when handling `Lifecycle::Started`.
+    fn exit_status(&self) -> Option<i32> {
+        None
+    }
+
+    /// How the transition from `previous` to this state is recorded for undo, when the
+    /// application keeps an undo history.
+    fn undo_step(&self, _previous: &Self) -> UndoStep {
+        UndoStep::Record
+    }
 }
 
 impl<S: State + Sized> From<S> for Component {
@@ -27,7 +80,79 @@ pub trait Renderer {
     fn render(&self, context: &mut RenderContext);
 }
 
-pub type ComponentId = ProcessUniqueId;
+/// Identifies a component within the process. Every component built gets a new id, so a rebuilt
+/// tree has different ids than the one it replaces.
+///
+/// Ids are normally handed out in whatever order the threads of the process build components.
+/// Components created inside `Component::build_seeded` get a seeded sequence instead, so that
+/// building the same trees in the same order gives ids that read the same on every run, e.g. to
+/// compare logs or replayed sessions. Seeded ids still never equal any other id of the process:
+/// every seeded build is told apart by a number that is left out when the id is formatted.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ComponentId {
+    /// The seeded build the id was handed out in, or 0 for process-wide ids.
+    build: u64,
+    value: u64,
+}
+
+static NEXT_UNIQUE_ID: AtomicUsize = AtomicUsize::new(0);
+static NEXT_SEEDED_BUILD: AtomicUsize = AtomicUsize::new(1);
+
+thread_local! {
+    /// The seeded build running on this thread, and the next value it hands out.
+    static SEEDED_BUILD: Cell<Option<(u64, u64)>> = Cell::new(None);
+}
+
+impl ComponentId {
+    pub fn new() -> ComponentId {
+        let seeded = SEEDED_BUILD.with(|current| {
+            current.get().map(|(build, value)| {
+                current.set(Some((build, value + 1)));
+                (build, value)
+            })
+        });
+
+        match seeded {
+            Some((build, value)) => ComponentId { build, value },
+            None => ComponentId { build: 0, value: NEXT_UNIQUE_ID.fetch_add(1, Ordering::Relaxed) as u64 }
+        }
+    }
+}
+
+impl Default for ComponentId {
+    fn default() -> ComponentId {
+        ComponentId::new()
+    }
+}
+
+impl fmt::Display for ComponentId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.build != 0 {
+            write!(f, "s{}", self.value)
+        } else {
+            write!(f, "{}", self.value)
+        }
+    }
+}
+
+impl fmt::Debug for ComponentId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ComponentId({})", self)
+    }
+}
+
+/// Puts back the seeded build that was running before, also when building panicked.
+struct RestoreSeededBuild(Option<(u64, u64)>);
+
+impl Drop for RestoreSeededBuild {
+    fn drop(&mut self) {
+        SEEDED_BUILD.with(|current| current.set(self.0));
+    }
+}
+
+/// Location of a component in its tree as the child indices leading to it from the root. Unlike a
+/// `ComponentId` it stays the same when the tree is rebuilt with the same structure.
+pub type ComponentPath = Vec<usize>;
 
 pub struct Component {
     id: ComponentId,
@@ -36,6 +161,8 @@ pub struct Component {
     children: Vec<Component>,
     callbacks: HashMap<TypeId, Box<StateCallback>>,
     styles: Vec<FlexStyle>,
+    transitions: Vec<Transition>,
+    captures_pointer: bool,
 }
 
 impl fmt::Debug for Component {
@@ -45,14 +172,24 @@ impl fmt::Debug for Component {
 }
 
 impl Component {
+    /// Calls `build`, giving the components it creates on this thread the ids `seed`, `seed + 1`
+    /// and so on, see `ComponentId`. Builds can be nested; the outer one goes on afterwards.
+    pub fn build_seeded<R, F: FnOnce() -> R>(seed: u64, build: F) -> R {
+        let build_number = NEXT_SEEDED_BUILD.fetch_add(1, Ordering::Relaxed) as u64;
+        let _restore = RestoreSeededBuild(SEEDED_BUILD.with(|current| current.replace(Some((build_number, seed)))));
+        build()
+    }
+
     pub fn new<R: Renderer + 'static>(renderer: R) -> Component {
         Component {
-            id: ProcessUniqueId::new(),
+            id: ComponentId::new(),
             name: None,
             renderer: Box::new(renderer),
             children: Vec::new(),
             callbacks: HashMap::new(),
             styles: Vec::new(),
+            transitions: Vec::new(),
+            captures_pointer: false,
         }
     }
 
@@ -79,6 +216,13 @@ impl Component {
         None
     }
 
+    pub fn find_by_path<'a>(&'a self, path: &[usize]) -> Option<&'a Component> {
+        match path.split_first() {
+            Some((index, rest)) => self.children.get(*index).and_then(|child| child.find_by_path(rest)),
+            None => Some(self)
+        }
+    }
+
     pub fn name<I: Into<String>>(mut self, name: I) -> Self {
         self.name = Some(name.into());
         self
@@ -117,6 +261,19 @@ impl Component {
         self
     }
 
+    /// Animates changes to a property when the component is rebuilt with another value for it.
+    pub fn transition(mut self, transition: Transition) -> Self {
+        self.transitions.push(transition);
+        self
+    }
+
+    /// When set, a press on this component captures the pointer: all moves and releases are sent
+    /// to it until the button is released or a rebuilt tree no longer sets this flag.
+    pub fn capture_pointer(mut self, capture: bool) -> Self {
+        self.captures_pointer = capture;
+        self
+    }
+
     pub fn inspect(&self) -> &InspectableComponent {
         self
     }
@@ -128,6 +285,10 @@ pub trait InspectableComponent {
     fn renderer(&self) -> &Renderer;
     fn children(&self) -> &Vec<Component>;
     fn styles(&self) -> &Vec<FlexStyle>;
+    fn transitions(&self) -> &Vec<Transition>;
+    fn captures_pointer(&self) -> bool;
+    /// Type names of the events the component handles, sorted.
+    fn event_types(&self) -> Vec<&'static str>;
 }
 
 impl InspectableComponent for Component {
@@ -150,6 +311,20 @@ impl InspectableComponent for Component {
     fn styles(&self) -> &Vec<FlexStyle> {
         &self.styles
     }
+
+    fn transitions(&self) -> &Vec<Transition> {
+        &self.transitions
+    }
+
+    fn captures_pointer(&self) -> bool {
+        self.captures_pointer
+    }
+
+    fn event_types(&self) -> Vec<&'static str> {
+        let mut event_types: Vec<&'static str> = self.callbacks.values().map(|callback| callback.event_type()).collect();
+        event_types.sort();
+        event_types
+    }
 }
 
 ////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
@@ -161,6 +336,7 @@ pub struct InvocationError;
 
 trait StateCallback {
     fn invoke(&self, state: &Any, event: &Any) -> Result<Box<Any>, InvocationError>;
+    fn event_type(&self) -> &'static str;
 }
 
 struct SyncStateCallback<S: State, E: Event> {
@@ -175,6 +351,10 @@ impl<S: State, E: Event> StateCallback for SyncStateCallback<S, E> {
         let new_state = (self.handler)(s.clone(), e)?;
         Ok(Box::new(new_state))
     }
+
+    fn event_type(&self) -> &'static str {
+        any::type_name::<E>()
+    }
 }
 
 ////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
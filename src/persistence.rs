@@ -0,0 +1,189 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+use std::str;
+
+#[derive(Debug)]
+pub enum PersistError {
+    Io(io::Error),
+    /// The format failed to serialize or deserialize the state.
+    Format(String),
+    /// The saved state has a version that no migration leads up from, or that is newer than the
+    /// current one.
+    Version(u32),
+    /// The file is not a saved state.
+    Corrupt,
+}
+
+impl From<io::Error> for PersistError {
+    fn from(e: io::Error) -> PersistError {
+        PersistError::Io(e)
+    }
+}
+
+impl fmt::Display for PersistError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PersistError::Io(ref e) => write!(f, "I/O error: {}", e),
+            PersistError::Format(ref message) => write!(f, "format error: {}", message),
+            PersistError::Version(version) => write!(f, "unsupported version: {}", version),
+            PersistError::Corrupt => write!(f, "not a saved state"),
+        }
+    }
+}
+
+/// Turns a state into bytes and back, e.g. with serde.
+pub trait Format<S> {
+    fn serialize(&self, state: &S) -> Result<Vec<u8>, PersistError>;
+    fn deserialize(&self, data: &[u8]) -> Result<S, PersistError>;
+}
+
+/// Upgrades the serialized state of one version to the next.
+pub type Migration = Box<Fn(Vec<u8>) -> Result<Vec<u8>, PersistError>>;
+
+/// Size of the main window in logical pixels, and its position as reported by the platform.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WindowGeometry {
+    pub size: (u32, u32),
+    pub position: Option<(i32, i32)>,
+}
+
+/// A state that was saved by `Persistence::save`.
+pub struct Saved<S> {
+    pub state: S,
+    pub geometry: Option<WindowGeometry>,
+}
+
+const MAGIC: &'static str = "weld-state";
+
+/// Saves states to a file and restores them. The serialized state is stored along with its
+/// version, so that states saved by an older version of the application can be migrated.
+pub struct Persistence<S> {
+    path: PathBuf,
+    format: Box<Format<S>>,
+    version: u32,
+    migrations: HashMap<u32, Migration>,
+    save_on_change: bool,
+}
+
+impl<S> Persistence<S> {
+    pub fn new<P: Into<PathBuf>>(path: P, format: Box<Format<S>>) -> Persistence<S> {
+        Persistence {
+            path: path.into(),
+            format,
+            version: 1,
+            migrations: HashMap::new(),
+            save_on_change: false,
+        }
+    }
+
+    /// The version of the state that is saved. Defaults to 1.
+    pub fn version(mut self, version: u32) -> Self {
+        self.version = version;
+        self
+    }
+
+    /// Registers how to upgrade a state saved with version `from` to version `from + 1`.
+    pub fn migration(mut self, from: u32, migration: Migration) -> Self {
+        self.migrations.insert(from, migration);
+        self
+    }
+
+    /// Saves after every state transition instead of only when the application quits. While the
+    /// application runs, the transitions up to the next frame are saved at once.
+    pub fn save_on_change(mut self, save_on_change: bool) -> Self {
+        self.save_on_change = save_on_change;
+        self
+    }
+
+    pub fn saves_on_change(&self) -> bool {
+        self.save_on_change
+    }
+
+    /// Restores the saved state, or returns None when nothing was saved yet.
+    pub fn load(&self) -> Result<Option<Saved<S>>, PersistError> {
+        let mut contents = Vec::new();
+        match File::open(&self.path) {
+            Ok(mut file) => file.read_to_end(&mut contents)?,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into())
+        };
+
+        let (version, geometry, mut data) = parse(contents)?;
+        if version > self.version {
+            return Err(PersistError::Version(version));
+        }
+        for from in version..self.version {
+            let migration = self.migrations.get(&from).ok_or(PersistError::Version(from))?;
+            data = migration(data)?;
+        }
+
+        Ok(Some(Saved {
+            state: self.format.deserialize(&data)?,
+            geometry,
+        }))
+    }
+
+    pub fn save(&self, state: &S, geometry: Option<&WindowGeometry>) -> Result<(), PersistError> {
+        let data = self.format.serialize(state)?;
+
+        // Write next to the file and move it in place, so a crash never leaves half a state behind
+        let mut temp_path = self.path.clone().into_os_string();
+        temp_path.push(".tmp");
+        {
+            let mut file = File::create(&temp_path)?;
+            writeln!(file, "{} {}", MAGIC, self.version)?;
+            if let Some(geometry) = geometry {
+                write!(file, "window {} {}", geometry.size.0, geometry.size.1)?;
+                if let Some((x, y)) = geometry.position {
+                    write!(file, " {} {}", x, y)?;
+                }
+                writeln!(file)?;
+            }
+            writeln!(file)?;
+            file.write_all(&data)?;
+            file.sync_all()?;
+        }
+        fs::rename(&temp_path, &self.path)?;
+
+        Ok(())
+    }
+}
+
+/// Splits a saved file into its version, window geometry and serialized state. The header lines
+/// end at the first empty line.
+fn parse(contents: Vec<u8>) -> Result<(u32, Option<WindowGeometry>, Vec<u8>), PersistError> {
+    let mut version = None;
+    let mut geometry = None;
+    let mut offset = 0;
+
+    loop {
+        let end = contents[offset..].iter().position(|b| *b == b'\n').ok_or(PersistError::Corrupt)? + offset;
+        let line = str::from_utf8(&contents[offset..end]).map_err(|_| PersistError::Corrupt)?;
+        offset = end + 1;
+
+        if line.is_empty() {
+            break;
+        }
+
+        let words: Vec<&str> = line.split(' ').collect();
+        match (words[0], version) {
+            (MAGIC, None) if words.len() == 2 => {
+                version = Some(words[1].parse().map_err(|_| PersistError::Corrupt)?);
+            },
+            ("window", Some(_)) if words.len() == 3 || words.len() == 5 => {
+                let number = |index: usize| words[index].parse::<i64>().map_err(|_| PersistError::Corrupt);
+                geometry = Some(WindowGeometry {
+                    size: (number(1)? as u32, number(2)? as u32),
+                    position: if words.len() == 5 { Some((number(3)? as i32, number(4)? as i32)) } else { None },
+                });
+            },
+            _ => return Err(PersistError::Corrupt)
+        }
+    }
+
+    let version = version.ok_or(PersistError::Corrupt)?;
+    Ok((version, geometry, contents[offset..].to_vec()))
+}
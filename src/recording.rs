@@ -0,0 +1,401 @@
+use glutin::{self, ElementState, VirtualKeyCode};
+use input::{Interaction, Modifiers, MouseButton, PointerEvent, TouchEvent, TouchPhase, WheelDelta, WheelEvent};
+use model::State;
+use application::Application;
+use window::WindowEvent;
+use webrender::api::{LayoutPoint, LayoutSize, WorldPoint};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const MAGIC: &'static str = "weld-recording 1";
+
+/// An input that can be recorded and replayed.
+#[derive(Clone, Debug)]
+pub enum RecordedInput {
+    /// The new size of the window in logical pixels.
+    Resized(LayoutSize),
+    Interaction(Interaction),
+    Touch(TouchEvent),
+    /// Only keys with a common virtual key code keep it, others are replayed with their scan code
+    /// alone.
+    Key(glutin::KeyboardInput),
+    /// The window was asked to close. Closing a child window closes it right away.
+    Closed,
+}
+
+impl RecordedInput {
+    /// Returns the recordable part of a window event, if any.
+    pub fn from_event(event: &WindowEvent) -> Option<RecordedInput> {
+        match *event {
+            WindowEvent::Resized(size) => Some(RecordedInput::Resized(size)),
+            WindowEvent::Interaction(_, ref interaction) => Some(RecordedInput::Interaction(interaction.clone())),
+            WindowEvent::Touch(ref touch) => Some(RecordedInput::Touch(touch.clone())),
+            WindowEvent::GlutinWindowEvent(glutin::WindowEvent::KeyboardInput { input, .. }) => Some(RecordedInput::Key(input)),
+            WindowEvent::WindowClosed => Some(RecordedInput::Closed),
+            _ => None
+        }
+    }
+
+    /// Parses an input written the way a `Recorder` writes it, without the offset and window that
+    /// start the line, e.g. `press 10 20 - left 1`. Its timestamps are set to now.
+    pub fn parse(text: &str) -> Option<RecordedInput> {
+        let words: Vec<&str> = text.split(' ').collect();
+        parse_input(words[0], &words[1..])
+    }
+
+    /// Moves the timestamps of the input to `timestamp`.
+    pub(crate) fn at(mut self, timestamp: Instant) -> RecordedInput {
+        match self {
+            RecordedInput::Interaction(Interaction::Pressed(ref mut e)) |
+            RecordedInput::Interaction(Interaction::Released(ref mut e)) |
+            RecordedInput::Interaction(Interaction::Moved(ref mut e)) => e.timestamp = timestamp,
+            RecordedInput::Interaction(Interaction::Wheel(ref mut e)) => e.timestamp = timestamp,
+            RecordedInput::Touch(ref mut touch) => touch.timestamp = timestamp,
+            _ => {}
+        }
+        self
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct RecordedEvent {
+    /// Time since the recording started.
+    pub offset: Duration,
+    /// The key of the child window that received the input, or None for the main window.
+    pub window: Option<String>,
+    pub input: RecordedInput,
+}
+
+/// Writes the inputs an application receives to a file as they happen, see
+/// `Application::record`. Every event is flushed right away, so a recording survives a crash.
+pub struct Recorder {
+    writer: Box<Write>,
+    start: Instant,
+}
+
+impl Recorder {
+    pub fn new<W: Write + 'static>(writer: W) -> io::Result<Recorder> {
+        let mut writer: Box<Write> = Box::new(writer);
+        writeln!(writer, "{}", MAGIC)?;
+        writer.flush()?;
+        Ok(Recorder {
+            writer,
+            start: Instant::now(),
+        })
+    }
+
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Recorder> {
+        Recorder::new(File::create(path)?)
+    }
+
+    pub fn record(&mut self, window: Option<&str>, input: &RecordedInput) {
+        let event = RecordedEvent {
+            offset: Instant::now().duration_since(self.start),
+            window: window.map(str::to_string),
+            input: input.clone(),
+        };
+
+        let result = writeln!(self.writer, "{}", format_event(&event)).and_then(|_| self.writer.flush());
+        if let Err(e) = result {
+            warn!("Could not record input: {}", e);
+        }
+    }
+}
+
+/// Inputs that were written by a `Recorder`.
+#[derive(Clone, Debug)]
+pub struct Recording {
+    pub events: Vec<RecordedEvent>,
+}
+
+impl Recording {
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Recording> {
+        Recording::read(File::open(path)?)
+    }
+
+    pub fn read<R: Read>(reader: R) -> io::Result<Recording> {
+        let mut lines = BufReader::new(reader).lines();
+        match lines.next() {
+            Some(Ok(ref line)) if line == MAGIC => {},
+            Some(Err(e)) => return Err(e),
+            _ => return Err(invalid("not a recording"))
+        }
+
+        let mut events = Vec::new();
+        for line in lines {
+            let line = line?;
+            if !line.is_empty() {
+                events.push(parse_event(&line).ok_or_else(|| invalid(&line))?);
+            }
+        }
+
+        Ok(Recording { events })
+    }
+
+    /// Feeds the inputs to the application one after the other, the same way `run` handles them.
+    /// Their timestamps keep the recorded distance, so clicks and drags are recognized as before,
+    /// but no time passes in between: long presses, which depend on the clock, don't fire.
+    ///
+    /// Component ids are seeded the way `Application::record` seeds them, so that the ids in
+    /// logs and histories match the recorded session.
+    pub fn replay<S: State>(&self, app: &mut Application<S>) {
+        app.ensure_seeded_component_ids();
+        let start = Instant::now();
+        for event in &self.events {
+            app.replay_input(event.window.as_ref().map(String::as_str), event.input.clone().at(start + event.offset));
+        }
+    }
+
+    /// Like `replay`, but waits for every input until as much time has passed as when it was
+    /// recorded.
+    pub fn replay_realtime<S: State>(&self, app: &mut Application<S>) {
+        app.ensure_seeded_component_ids();
+        let start = Instant::now();
+        for event in &self.events {
+            let due = start + event.offset;
+            let now = Instant::now();
+            if due > now {
+                thread::sleep(due - now);
+            }
+            app.replay_input(event.window.as_ref().map(String::as_str), event.input.clone().at(due));
+        }
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("invalid recording: {}", message))
+}
+
+// One event per line: the offset in microseconds, the window, and the input with its arguments,
+// separated by spaces.
+
+fn format_event(event: &RecordedEvent) -> String {
+    let micros = event.offset.as_secs() * 1_000_000 + (event.offset.subsec_nanos() / 1000) as u64;
+    let window = match event.window {
+        Some(ref key) => escape(key),
+        None => "-".to_string()
+    };
+
+    let input = match event.input {
+        RecordedInput::Resized(size) => format!("resize {} {}", size.width, size.height),
+        RecordedInput::Interaction(Interaction::Pressed(ref e)) => format!("press {}", format_pointer(e)),
+        RecordedInput::Interaction(Interaction::Released(ref e)) => format!("release {}", format_pointer(e)),
+        RecordedInput::Interaction(Interaction::Moved(ref e)) => format!("move {}", format_pointer(e)),
+        RecordedInput::Interaction(Interaction::Wheel(ref e)) => {
+            let (unit, x, y) = match e.delta {
+                WheelDelta::Lines(x, y) => ("lines", x, y),
+                WheelDelta::Pixels(x, y) => ("pixels", x, y),
+            };
+            format!("wheel {} {} {} {} {} {}", e.window_position.x, e.window_position.y, format_modifiers(e.modifiers), unit, x, y)
+        },
+        RecordedInput::Touch(ref touch) => {
+            let phase = match touch.phase {
+                TouchPhase::Started => "started",
+                TouchPhase::Moved => "moved",
+                TouchPhase::Ended => "ended",
+                TouchPhase::Cancelled => "cancelled",
+            };
+            format!("touch {} {} {} {}", touch.window_position.x, touch.window_position.y, touch.id, phase)
+        },
+        RecordedInput::Key(ref input) => {
+            let state = match input.state {
+                ElementState::Pressed => "pressed",
+                ElementState::Released => "released",
+            };
+            let key = input.virtual_keycode.and_then(key_code_name).unwrap_or("-");
+            format!("key {} {} {} {}", input.scancode, state, key, format_modifiers(input.modifiers.into()))
+        },
+        RecordedInput::Closed => "close".to_string(),
+    };
+
+    format!("{} {} {}", micros, window, input)
+}
+
+fn format_pointer(e: &PointerEvent) -> String {
+    let button = match e.button {
+        Some(MouseButton::Left) => "left".to_string(),
+        Some(MouseButton::Right) => "right".to_string(),
+        Some(MouseButton::Middle) => "middle".to_string(),
+        Some(MouseButton::Other(n)) => n.to_string(),
+        None => "-".to_string()
+    };
+    format!("{} {} {} {} {}", e.window_position.x, e.window_position.y, format_modifiers(e.modifiers), button, e.click_count)
+}
+
+fn format_modifiers(modifiers: Modifiers) -> String {
+    let mut result = String::new();
+    for &(held, flag) in &[(modifiers.ctrl, 'c'), (modifiers.shift, 's'), (modifiers.alt, 'a'), (modifiers.logo, 'l')] {
+        if held {
+            result.push(flag);
+        }
+    }
+    if result.is_empty() {
+        result.push('-');
+    }
+    result
+}
+
+fn parse_event(line: &str) -> Option<RecordedEvent> {
+    let words: Vec<&str> = line.split(' ').collect();
+    if words.len() < 3 {
+        return None;
+    }
+
+    let micros: u64 = words[0].parse().ok()?;
+    let window = match words[1] {
+        "-" => None,
+        key => Some(unescape(key))
+    };
+    let input = parse_input(words[2], &words[3..])?;
+
+    Some(RecordedEvent {
+        offset: Duration::new(micros / 1_000_000, (micros % 1_000_000) as u32 * 1000),
+        window,
+        input,
+    })
+}
+
+/// Parses the part of a line that follows the offset and window.
+fn parse_input(kind: &str, args: &[&str]) -> Option<RecordedInput> {
+    let number = |index: usize| args.get(index).and_then(|word| word.parse::<f32>().ok());
+    let timestamp = Instant::now();
+
+    let input = match (kind, args.len()) {
+        ("resize", 2) => RecordedInput::Resized(LayoutSize::new(number(0)?, number(1)?)),
+        ("press", 5) | ("release", 5) | ("move", 5) => {
+            let position = WorldPoint::new(number(0)?, number(1)?);
+            let event = PointerEvent {
+                button: match args[3] {
+                    "left" => Some(MouseButton::Left),
+                    "right" => Some(MouseButton::Right),
+                    "middle" => Some(MouseButton::Middle),
+                    "-" => None,
+                    n => Some(MouseButton::Other(n.parse().ok()?))
+                },
+                click_count: args[4].parse().ok()?,
+                modifiers: parse_modifiers(args[2])?,
+                window_position: position,
+                local_position: LayoutPoint::new(position.x, position.y),
+                timestamp,
+            };
+            RecordedInput::Interaction(match kind {
+                "press" => Interaction::Pressed(event),
+                "release" => Interaction::Released(event),
+                _ => Interaction::Moved(event)
+            })
+        },
+        ("wheel", 6) => {
+            let position = WorldPoint::new(number(0)?, number(1)?);
+            let delta = match args[3] {
+                "lines" => WheelDelta::Lines(number(4)?, number(5)?),
+                "pixels" => WheelDelta::Pixels(number(4)?, number(5)?),
+                _ => return None
+            };
+            RecordedInput::Interaction(Interaction::Wheel(WheelEvent {
+                delta,
+                modifiers: parse_modifiers(args[2])?,
+                window_position: position,
+                local_position: LayoutPoint::new(position.x, position.y),
+                timestamp,
+            }))
+        },
+        ("touch", 4) => {
+            let position = WorldPoint::new(number(0)?, number(1)?);
+            RecordedInput::Touch(TouchEvent {
+                id: args[2].parse().ok()?,
+                phase: match args[3] {
+                    "started" => TouchPhase::Started,
+                    "moved" => TouchPhase::Moved,
+                    "ended" => TouchPhase::Ended,
+                    "cancelled" => TouchPhase::Cancelled,
+                    _ => return None
+                },
+                window_position: position,
+                local_position: LayoutPoint::new(position.x, position.y),
+                timestamp,
+            })
+        },
+        ("key", 4) => {
+            let modifiers = parse_modifiers(args[3])?;
+            RecordedInput::Key(glutin::KeyboardInput {
+                scancode: args[0].parse().ok()?,
+                state: match args[1] {
+                    "pressed" => ElementState::Pressed,
+                    "released" => ElementState::Released,
+                    _ => return None
+                },
+                virtual_keycode: key_code_from_name(args[2]),
+                modifiers: glutin::ModifiersState {
+                    shift: modifiers.shift,
+                    ctrl: modifiers.ctrl,
+                    alt: modifiers.alt,
+                    logo: modifiers.logo,
+                },
+            })
+        },
+        ("close", 0) => RecordedInput::Closed,
+        _ => return None
+    };
+
+    Some(input)
+}
+
+fn parse_modifiers(word: &str) -> Option<Modifiers> {
+    let mut modifiers = Modifiers::none();
+    if word == "-" {
+        return Some(modifiers);
+    }
+
+    for flag in word.chars() {
+        match flag {
+            'c' => modifiers.ctrl = true,
+            's' => modifiers.shift = true,
+            'a' => modifiers.alt = true,
+            'l' => modifiers.logo = true,
+            _ => return None
+        }
+    }
+    Some(modifiers)
+}
+
+/// Window keys are written as a single word, so spaces are percent-encoded.
+fn escape(key: &str) -> String {
+    key.replace('%', "%25").replace(' ', "%20")
+}
+
+fn unescape(word: &str) -> String {
+    word.replace("%20", " ").replace("%25", "%")
+}
+
+macro_rules! key_codes {
+    ($($name:ident),*) => {
+        fn key_code_name(code: VirtualKeyCode) -> Option<&'static str> {
+            match code {
+                $(VirtualKeyCode::$name => Some(stringify!($name)),)*
+                _ => None
+            }
+        }
+
+        fn key_code_from_name(name: &str) -> Option<VirtualKeyCode> {
+            match name {
+                $(stringify!($name) => Some(VirtualKeyCode::$name),)*
+                _ => None
+            }
+        }
+    }
+}
+
+key_codes!(
+    Key1, Key2, Key3, Key4, Key5, Key6, Key7, Key8, Key9, Key0,
+    A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z,
+    Escape, F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12,
+    Insert, Home, Delete, End, PageDown, PageUp, Left, Up, Right, Down,
+    Back, Return, Space, Tab,
+    LAlt, LControl, LShift, LWin, RAlt, RControl, RShift, RWin,
+    Minus, Equals, Comma, Period, Slash, Semicolon, Apostrophe, Backslash, LBracket, RBracket, Grave,
+    Numpad0, Numpad1, Numpad2, Numpad3, Numpad4, Numpad5, Numpad6, Numpad7, Numpad8, Numpad9,
+    Add, Subtract, Multiply, Divide, Decimal, NumpadEnter
+);
@@ -0,0 +1,548 @@
+//! Remote inspection over a local socket. Clients send one JSON request per line and get one JSON
+//! response per line:
+//!
+//! ```text
+//! {"id": 1, "method": "tree", "params": {"layout": true}}
+//! {"id": 1, "result": {"path": [], "renderer": "Container", ...}}
+//! ```
+//!
+//! Requests are limited to `MAX_REQUEST_LENGTH` bytes; the connection of a client that sends a
+//! longer one is closed. The `id` is passed back as is. Failed requests get `{"id": ..., "error": "..."}` instead of a
+//! result. Methods that work on a window take an optional `window` parameter with the key of a
+//! child window, and work on the main window without it.
+//!
+//! - `tree`: the component tree, with the computed bounds of every component if `layout` is true.
+//!   Bounds are relative to the window, like the positions of injected inputs.
+//! - `windows`: the keys of the open child windows.
+//! - `state`: the state, formatted with `Debug`.
+//! - `inject`: handles the `input` parameter like an input the window received. Inputs are written
+//!   the way a `Recorder` writes them, e.g. `press 10 20 - left 1`.
+//! - `screenshot`: the contents of the window as base64-encoded RGBA pixels.
+
+use application::{Application, Dispatcher};
+use layout_context::LayoutContext;
+use model::{InspectableComponent, State};
+use recording::RecordedInput;
+use window::Screenshot;
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+#[cfg(unix)]
+use std::path::Path;
+use std::str::Chars;
+use std::iter::Peekable;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Instant;
+
+/// A JSON value.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    /// Keys keep their order.
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    pub fn parse(text: &str) -> Result<Json, String> {
+        let mut chars = text.chars().peekable();
+        let value = parse_value(&mut chars, 0)?;
+        skip_whitespace(&mut chars);
+        match chars.next() {
+            None => Ok(value),
+            Some(c) => Err(format!("unexpected '{}' after the value", c))
+        }
+    }
+
+    /// The value of a key of an object.
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match *self {
+            Json::Object(ref entries) => entries.iter().find(|&&(ref k, _)| k == key).map(|&(_, ref value)| value),
+            _ => None
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match *self {
+            Json::String(ref value) => Some(value),
+            _ => None
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            Json::Number(value) => Some(value),
+            _ => None
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match *self {
+            Json::Bool(value) => Some(value),
+            _ => None
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&Vec<Json>> {
+        match *self {
+            Json::Array(ref values) => Some(values),
+            _ => None
+        }
+    }
+}
+
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(value) => write!(f, "{}", value),
+            // JSON has no representation for these
+            Json::Number(value) if !value.is_finite() => write!(f, "null"),
+            Json::Number(value) => write!(f, "{}", value),
+            Json::String(ref value) => write_string(f, value),
+            Json::Array(ref values) => {
+                write!(f, "[")?;
+                for (index, value) in values.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", value)?;
+                }
+                write!(f, "]")
+            },
+            Json::Object(ref entries) => {
+                write!(f, "{{")?;
+                for (index, &(ref key, ref value)) in entries.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ",")?;
+                    }
+                    write_string(f, key)?;
+                    write!(f, ":{}", value)?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter, value: &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for c in value.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?
+        }
+    }
+    write!(f, "\"")
+}
+
+fn skip_whitespace(chars: &mut Peekable<Chars>) {
+    while chars.peek().map_or(false, |c| c.is_whitespace()) {
+        chars.next();
+    }
+}
+
+fn expect_word(chars: &mut Peekable<Chars>, word: &str) -> Result<(), String> {
+    for expected in word.chars() {
+        if chars.next() != Some(expected) {
+            return Err(format!("expected {}", word));
+        }
+    }
+    Ok(())
+}
+
+/// Arrays and objects nested deeper than this are rejected, so that a request can't exhaust the
+/// stack.
+const MAX_DEPTH: usize = 64;
+
+fn parse_value(chars: &mut Peekable<Chars>, depth: usize) -> Result<Json, String> {
+    skip_whitespace(chars);
+    if depth >= MAX_DEPTH && (chars.peek() == Some(&'[') || chars.peek() == Some(&'{')) {
+        return Err("nested too deeply".to_string());
+    }
+    match chars.peek().cloned() {
+        Some('n') => expect_word(chars, "null").map(|_| Json::Null),
+        Some('t') => expect_word(chars, "true").map(|_| Json::Bool(true)),
+        Some('f') => expect_word(chars, "false").map(|_| Json::Bool(false)),
+        Some('"') => parse_string(chars).map(Json::String),
+        Some('[') => {
+            chars.next();
+            let mut values = Vec::new();
+            skip_whitespace(chars);
+            if chars.peek() == Some(&']') {
+                chars.next();
+                return Ok(Json::Array(values));
+            }
+            loop {
+                values.push(parse_value(chars, depth + 1)?);
+                skip_whitespace(chars);
+                match chars.next() {
+                    Some(',') => {},
+                    Some(']') => return Ok(Json::Array(values)),
+                    _ => return Err("expected ',' or ']'".to_string())
+                }
+            }
+        },
+        Some('{') => {
+            chars.next();
+            let mut entries = Vec::new();
+            skip_whitespace(chars);
+            if chars.peek() == Some(&'}') {
+                chars.next();
+                return Ok(Json::Object(entries));
+            }
+            loop {
+                skip_whitespace(chars);
+                let key = parse_string(chars)?;
+                skip_whitespace(chars);
+                if chars.next() != Some(':') {
+                    return Err("expected ':'".to_string());
+                }
+                entries.push((key, parse_value(chars, depth + 1)?));
+                skip_whitespace(chars);
+                match chars.next() {
+                    Some(',') => {},
+                    Some('}') => return Ok(Json::Object(entries)),
+                    _ => return Err("expected ',' or '}'".to_string())
+                }
+            }
+        },
+        Some(c) if c == '-' || c.is_digit(10) => {
+            let mut number = String::new();
+            while chars.peek().map_or(false, |c| c.is_digit(10) || "+-.eE".contains(*c)) {
+                number.push(chars.next().unwrap());
+            }
+            number.parse().map(Json::Number).map_err(|_| format!("invalid number {}", number))
+        },
+        Some(c) => Err(format!("unexpected '{}'", c)),
+        None => Err("unexpected end".to_string())
+    }
+}
+
+fn parse_string(chars: &mut Peekable<Chars>) -> Result<String, String> {
+    if chars.next() != Some('"') {
+        return Err("expected a string".to_string());
+    }
+
+    let mut value = String::new();
+    loop {
+        match chars.next() {
+            Some('"') => return Ok(value),
+            Some('\\') => {
+                let c = match chars.next() {
+                    Some('"') => '"',
+                    Some('\\') => '\\',
+                    Some('/') => '/',
+                    Some('b') => '\u{8}',
+                    Some('f') => '\u{c}',
+                    Some('n') => '\n',
+                    Some('r') => '\r',
+                    Some('t') => '\t',
+                    Some('u') => {
+                        let mut code = parse_hex(chars)?;
+                        // Characters outside the basic plane come as a surrogate pair
+                        if code >= 0xd800 && code < 0xdc00 {
+                            expect_word(chars, "\\u")?;
+                            let low = parse_hex(chars)?;
+                            if low < 0xdc00 || low > 0xdfff {
+                                return Err(format!("invalid low surrogate \\u{:04x}", low));
+                            }
+                            code = 0x10000 + ((code - 0xd800) << 10) + (low - 0xdc00);
+                        }
+                        ::std::char::from_u32(code).ok_or("invalid character")?
+                    },
+                    _ => return Err("invalid escape".to_string())
+                };
+                value.push(c);
+            },
+            Some(c) => value.push(c),
+            None => return Err("unterminated string".to_string())
+        }
+    }
+}
+
+fn parse_hex(chars: &mut Peekable<Chars>) -> Result<u32, String> {
+    let digits: String = chars.by_ref().take(4).collect();
+    if digits.len() < 4 || !digits.chars().all(|c| c.is_digit(16)) {
+        return Err(format!("invalid escape \\u{}", digits));
+    }
+    u32::from_str_radix(&digits, 16).map_err(|_| format!("invalid escape \\u{}", digits))
+}
+
+enum Listener {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix(UnixListener),
+}
+
+/// Listens for remote inspection clients, see `Application::remote`. Only bind to local
+/// addresses: anyone who can connect can read the state and drive the application.
+pub struct RemoteServer {
+    listener: Listener,
+}
+
+impl RemoteServer {
+    pub fn bind_tcp<A: ToSocketAddrs>(address: A) -> io::Result<RemoteServer> {
+        Ok(RemoteServer { listener: Listener::Tcp(TcpListener::bind(address)?) })
+    }
+
+    #[cfg(unix)]
+    pub fn bind_unix<P: AsRef<Path>>(path: P) -> io::Result<RemoteServer> {
+        Ok(RemoteServer { listener: Listener::Unix(UnixListener::bind(path)?) })
+    }
+
+    /// The address of a TCP server, e.g. to find out which port was picked for port 0.
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        match self.listener {
+            Listener::Tcp(ref listener) => listener.local_addr().ok(),
+            #[cfg(unix)]
+            Listener::Unix(_) => None
+        }
+    }
+
+    /// Accepts clients on a thread of its own. Every client gets a thread that passes its
+    /// requests to the application through the dispatcher.
+    pub(crate) fn start<S: State + fmt::Debug>(self, dispatcher: Dispatcher<S>) {
+        thread::spawn(move || {
+            match self.listener {
+                Listener::Tcp(listener) => {
+                    for stream in listener.incoming() {
+                        match stream.and_then(|stream| Ok((stream.try_clone()?, stream))) {
+                            Ok((reader, writer)) => serve(reader, writer, dispatcher.clone()),
+                            Err(e) => warn!("Could not accept remote client: {}", e)
+                        }
+                    }
+                },
+                #[cfg(unix)]
+                Listener::Unix(listener) => {
+                    for stream in listener.incoming() {
+                        match stream.and_then(|stream| Ok((stream.try_clone()?, stream))) {
+                            Ok((reader, writer)) => serve(reader, writer, dispatcher.clone()),
+                            Err(e) => warn!("Could not accept remote client: {}", e)
+                        }
+                    }
+                }
+            }
+        });
+    }
+}
+
+/// The longest request a client may send, including its line break.
+pub const MAX_REQUEST_LENGTH: usize = 1024 * 1024;
+
+fn serve<R, W, S>(reader: R, mut writer: W, dispatcher: Dispatcher<S>)
+    where R: io::Read + Send + 'static, W: Write + Send + 'static, S: State + fmt::Debug
+{
+    thread::spawn(move || {
+        let mut reader = BufReader::new(reader);
+        loop {
+            let mut line = Vec::new();
+            match io::Read::take(&mut reader, MAX_REQUEST_LENGTH as u64 + 1).read_until(b'\n', &mut line) {
+                Ok(0) | Err(_) => return,
+                Ok(_) => {}
+            }
+            // The rest of the request can't be told apart from the next one, so give up on the client
+            if line.len() > MAX_REQUEST_LENGTH {
+                let _ = writeln!(writer, "{}", error_response(Json::Null, "request too long"));
+                return;
+            }
+            let line = match String::from_utf8(line) {
+                Ok(line) => line,
+                Err(_) => return
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let response = match Json::parse(&line) {
+                Ok(request) => {
+                    let (reply_tx, reply_rx) = mpsc::channel();
+                    dispatcher.post(Box::new(move |application: &mut Application<S>| {
+                        let _ = reply_tx.send(handle_request(application, &request));
+                    }));
+                    match reply_rx.recv() {
+                        Ok(response) => response,
+                        // The application has quit
+                        Err(_) => return
+                    }
+                },
+                Err(e) => error_response(Json::Null, &format!("invalid request: {}", e))
+            };
+
+            if writeln!(writer, "{}", response).and_then(|_| writer.flush()).is_err() {
+                return;
+            }
+        }
+    });
+}
+
+fn error_response(id: Json, message: &str) -> Json {
+    Json::Object(vec![
+        ("id".to_string(), id),
+        ("error".to_string(), Json::String(message.to_string())),
+    ])
+}
+
+fn handle_request<S: State + fmt::Debug>(application: &mut Application<S>, request: &Json) -> Json {
+    let id = request.get("id").cloned().unwrap_or(Json::Null);
+    let no_params = Json::Object(Vec::new());
+    let params = request.get("params").unwrap_or(&no_params);
+    let window = params.get("window").and_then(Json::as_str);
+
+    let result = match request.get("method").and_then(Json::as_str) {
+        Some("tree") => {
+            let layout = params.get("layout").and_then(Json::as_bool).unwrap_or(false);
+            application.with_window(window, |root, layout_context| {
+                describe_component(root.inspect(), &mut Vec::new(), if layout { Some(layout_context) } else { None })
+            }).ok_or("no such window")
+        },
+        Some("windows") => {
+            Ok(Json::Array(application.child_windows().into_iter().map(|key| Json::String(key.to_string())).collect()))
+        },
+        Some("state") => Ok(Json::String(format!("{:#?}", application.state()))),
+        Some("inject") => {
+            match params.get("input").and_then(Json::as_str).and_then(RecordedInput::parse) {
+                Some(input) => {
+                    application.replay_input(window, input.at(Instant::now()));
+                    Ok(Json::Null)
+                },
+                None => Err("invalid input")
+            }
+        },
+        Some("screenshot") => application.screenshot(window).map(|screenshot| Json::from(&screenshot)).ok_or("the window isn't open"),
+        _ => Err("unknown method")
+    };
+
+    match result {
+        Ok(result) => Json::Object(vec![
+            ("id".to_string(), id),
+            ("result".to_string(), result),
+        ]),
+        Err(message) => error_response(id, message)
+    }
+}
+
+fn describe_component(component: &InspectableComponent, path: &mut Vec<usize>, layout_context: Option<&LayoutContext>) -> Json {
+    let strings = |values: Vec<String>| Json::Array(values.into_iter().map(Json::String).collect());
+
+    let mut entries = vec![
+        ("path".to_string(), Json::Array(path.iter().map(|index| Json::Number(*index as f64)).collect())),
+        ("renderer".to_string(), Json::String(component.renderer().id().to_string())),
+        ("name".to_string(), component.name().clone().map_or(Json::Null, Json::String)),
+        ("styles".to_string(), strings(component.styles().iter().map(|style| format!("{:?}", style)).collect())),
+        ("events".to_string(), strings(component.event_types().iter().map(|event_type| event_type.to_string()).collect())),
+    ];
+
+    if let Some(layout_context) = layout_context {
+        let bounds = layout_context.absolute_bounds(component);
+        entries.push(("bounds".to_string(), Json::Object(vec![
+            ("x".to_string(), Json::Number(bounds.origin.x as f64)),
+            ("y".to_string(), Json::Number(bounds.origin.y as f64)),
+            ("width".to_string(), Json::Number(bounds.size.width as f64)),
+            ("height".to_string(), Json::Number(bounds.size.height as f64)),
+        ])));
+    }
+
+    let children = component.children().iter().enumerate().map(|(index, child)| {
+        path.push(index);
+        let child = describe_component(child.inspect(), path, layout_context);
+        path.pop();
+        child
+    }).collect();
+    entries.push(("children".to_string(), Json::Array(children)));
+
+    Json::Object(entries)
+}
+
+/// The result of a `screenshot` request.
+impl<'a> From<&'a Screenshot> for Json {
+    fn from(screenshot: &Screenshot) -> Json {
+        Json::Object(vec![
+            ("width".to_string(), Json::Number(screenshot.width as f64)),
+            ("height".to_string(), Json::Number(screenshot.height as f64)),
+            ("format".to_string(), Json::String("rgba8".to_string())),
+            ("data".to_string(), Json::String(base64(&screenshot.pixels))),
+        ])
+    }
+}
+
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &'static [u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut output = String::with_capacity((data.len() + 2) / 3 * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let bits = (bytes[0] as u32) << 16 | (bytes[1] as u32) << 8 | bytes[2] as u32;
+        for index in 0..4 {
+            if index <= chunk.len() {
+                output.push(ALPHABET[(bits >> (18 - index * 6) & 0x3f) as usize] as char);
+            } else {
+                output.push('=');
+            }
+        }
+    }
+    output
+}
+
+/// A client for the remote inspection protocol, e.g. for tests.
+pub struct RemoteClient {
+    reader: BufReader<Box<io::Read + Send>>,
+    writer: Box<Write + Send>,
+    next_id: u32,
+}
+
+impl RemoteClient {
+    pub fn connect_tcp<A: ToSocketAddrs>(address: A) -> io::Result<RemoteClient> {
+        let stream = TcpStream::connect(address)?;
+        Ok(RemoteClient::new(Box::new(stream.try_clone()?), Box::new(stream)))
+    }
+
+    #[cfg(unix)]
+    pub fn connect_unix<P: AsRef<Path>>(path: P) -> io::Result<RemoteClient> {
+        let stream = UnixStream::connect(path)?;
+        Ok(RemoteClient::new(Box::new(stream.try_clone()?), Box::new(stream)))
+    }
+
+    fn new(reader: Box<io::Read + Send>, writer: Box<Write + Send>) -> RemoteClient {
+        RemoteClient {
+            reader: BufReader::new(reader),
+            writer,
+            next_id: 0,
+        }
+    }
+
+    /// Sends a request and waits for its response. Returns the result, or the error message the
+    /// application responded with as an error of kind `Other`.
+    pub fn request(&mut self, method: &str, params: Json) -> io::Result<Json> {
+        self.next_id += 1;
+        let request = Json::Object(vec![
+            ("id".to_string(), Json::Number(self.next_id as f64)),
+            ("method".to_string(), Json::String(method.to_string())),
+            ("params".to_string(), params),
+        ]);
+        writeln!(self.writer, "{}", request)?;
+        self.writer.flush()?;
+
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "the application has quit"));
+        }
+
+        let response = Json::parse(&line).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        if let Some(error) = response.get("error") {
+            return Err(io::Error::new(io::ErrorKind::Other, error.as_str().unwrap_or("unknown error").to_string()));
+        }
+        Ok(response.get("result").cloned().unwrap_or(Json::Null))
+    }
+}
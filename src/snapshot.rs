@@ -0,0 +1,130 @@
+use layout_context::LayoutContext;
+use model::{Component, InspectableComponent};
+use std::env;
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+/// Describes a component tree as text, one component per line followed by its details, indented
+/// by depth. Components are identified by their path, which stays the same across runs, unlike
+/// their `ComponentId`. Handled event types are listed in sorted order, so equal trees always
+/// give the same text.
+///
+/// ```text
+/// [] Container "todo-list"
+///     styles: [Width(Percent(100.0))]
+///     events: [weld::gesture::Gesture]
+///     [0] Item
+/// ```
+pub fn snapshot(root: &Component) -> String {
+    let mut output = String::new();
+    write_component(&mut output, root.inspect(), &mut Vec::new(), None);
+    output
+}
+
+/// Like `snapshot`, but also includes the bounds of every component, relative to its parent. The
+/// tree must have been laid out by `layout_context`.
+pub fn snapshot_with_layout(root: &Component, layout_context: &LayoutContext) -> String {
+    let mut output = String::new();
+    write_component(&mut output, root.inspect(), &mut Vec::new(), Some(layout_context));
+    output
+}
+
+fn write_component(output: &mut String, component: &InspectableComponent, path: &mut Vec<usize>, layout_context: Option<&LayoutContext>) {
+    let indent = "    ".repeat(path.len());
+
+    write!(output, "{}{:?} {}", indent, path, component.renderer().id()).unwrap();
+    if let Some(ref name) = *component.name() {
+        write!(output, " {:?}", name).unwrap();
+    }
+    output.push('\n');
+
+    if !component.styles().is_empty() {
+        writeln!(output, "{}    styles: {:?}", indent, component.styles()).unwrap();
+    }
+    let event_types = component.event_types();
+    if !event_types.is_empty() {
+        writeln!(output, "{}    events: [{}]", indent, event_types.join(", ")).unwrap();
+    }
+    if component.captures_pointer() {
+        writeln!(output, "{}    captures pointer", indent).unwrap();
+    }
+    if let Some(layout_context) = layout_context {
+        let bounds = layout_context.get_bounds(component);
+        writeln!(output, "{}    bounds: ({}, {}) {}x{}", indent, bounds.origin.x, bounds.origin.y, bounds.size.width, bounds.size.height).unwrap();
+    }
+
+    for (index, child) in component.children().iter().enumerate() {
+        path.push(index);
+        write_component(output, child.inspect(), path, layout_context);
+        path.pop();
+    }
+}
+
+/// Compares two texts line by line. Returns None when they are equal, or the lines of both with
+/// those only in `expected` marked with `-` and those only in `actual` marked with `+`.
+pub fn diff(actual: &str, expected: &str) -> Option<String> {
+    if actual == expected {
+        return None;
+    }
+
+    let expected: Vec<&str> = expected.lines().collect();
+    let actual: Vec<&str> = actual.lines().collect();
+
+    // Length of the longest common subsequence of the remaining lines, for every pair of offsets
+    let mut common = vec![vec![0usize; actual.len() + 1]; expected.len() + 1];
+    for i in (0..expected.len()).rev() {
+        for j in (0..actual.len()).rev() {
+            common[i][j] = if expected[i] == actual[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let mut output = String::new();
+    let (mut i, mut j) = (0, 0);
+    while i < expected.len() || j < actual.len() {
+        if i < expected.len() && j < actual.len() && expected[i] == actual[j] {
+            writeln!(output, "  {}", expected[i]).unwrap();
+            i += 1;
+            j += 1;
+        } else if j < actual.len() && (i == expected.len() || common[i][j + 1] > common[i + 1][j]) {
+            writeln!(output, "+ {}", actual[j]).unwrap();
+            j += 1;
+        } else {
+            writeln!(output, "- {}", expected[i]).unwrap();
+            i += 1;
+        }
+    }
+
+    // Texts that only differ in a trailing newline have the same lines
+    if output.lines().all(|line| line.starts_with("  ")) {
+        output.push_str("(the texts differ in their line endings)\n");
+    }
+
+    Some(output)
+}
+
+/// Panics with the differences when `actual` isn't equal to `expected`.
+pub fn assert_snapshot(actual: &str, expected: &str) {
+    if let Some(diff) = diff(actual, expected) {
+        panic!("snapshot doesn't match (- expected, + actual):\n{}", diff);
+    }
+}
+
+/// Like `assert_snapshot`, but compares with the contents of a file. The file is written instead
+/// when it doesn't exist yet, or when the `WELD_UPDATE_SNAPSHOTS` environment variable is set.
+pub fn assert_snapshot_file<P: AsRef<Path>>(actual: &str, path: P) {
+    let path = path.as_ref();
+    if env::var_os("WELD_UPDATE_SNAPSHOTS").is_some() || !path.exists() {
+        fs::write(path, actual).expect("could not write snapshot");
+        return;
+    }
+
+    let expected = fs::read_to_string(path).expect("could not read snapshot");
+    if let Some(diff) = diff(actual, &expected) {
+        panic!("snapshot {} doesn't match (- expected, + actual):\n{}", path.display(), diff);
+    }
+}
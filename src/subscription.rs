@@ -0,0 +1,167 @@
+use futures::{Async, Stream};
+use futures::executor::{self, Notify, NotifyHandle, Spawn};
+use model::{InvocationError, State};
+use std::fmt::Debug;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+type SubscriptionHandler<S, T> = Box<Fn(S, &T) -> Result<S, InvocationError>>;
+
+/// The most items a subscription hands over per `Application::poll_subscriptions`, so that a
+/// stream that always has an item ready can't keep the event loop from handling anything else.
+pub const MAX_ITEMS_PER_POLL: usize = 32;
+
+/// Passed to the handler of a timer subscription when it fires.
+#[derive(Clone, Debug)]
+pub struct Tick {
+    pub now: Instant,
+}
+
+/// An event source the state asks for through `State::subscriptions()`. Subscriptions are told
+/// apart by their key: a subscription keeps running for as long as the state returns one with the
+/// same key, and is started or stopped when a key appears or disappears.
+pub struct Subscription<S: State> {
+    key: String,
+    start: Box<Fn(Instant) -> Box<Source<S>>>,
+}
+
+impl<S: State> Subscription<S> {
+    /// Fires every `period` after the subscription started. Periods that were missed, e.g. because
+    /// the application was busy, are skipped instead of fired in a burst.
+    pub fn interval<K: Into<String>>(key: K, period: Duration, handler: SubscriptionHandler<S, Tick>) -> Subscription<S> {
+        Subscription::timer(key.into(), period, true, handler)
+    }
+
+    /// Fires once, `delay` after the subscription started.
+    pub fn timeout<K: Into<String>>(key: K, delay: Duration, handler: SubscriptionHandler<S, Tick>) -> Subscription<S> {
+        Subscription::timer(key.into(), delay, false, handler)
+    }
+
+    /// Feeds the items of the stream created by `start` through the handler. The stream is created
+    /// when the subscription starts and dropped when it stops; it ends on its first error.
+    pub fn stream<K, F, St>(key: K, start: F, handler: SubscriptionHandler<S, St::Item>) -> Subscription<S>
+        where K: Into<String>, F: Fn() -> St + 'static, St: Stream + 'static, St::Error: Debug
+    {
+        let handler = Rc::new(handler);
+        Subscription {
+            key: key.into(),
+            start: Box::new(move |_| Box::new(StreamSource {
+                stream: Some(executor::spawn(start())),
+                handler: handler.clone(),
+            })),
+        }
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    fn timer(key: String, period: Duration, repeat: bool, handler: SubscriptionHandler<S, Tick>) -> Subscription<S> {
+        let handler = Rc::new(handler);
+        Subscription {
+            key,
+            start: Box::new(move |now| Box::new(TimerSource {
+                period,
+                repeat,
+                due: Some(now + period),
+                handler: handler.clone(),
+            })),
+        }
+    }
+
+    pub(crate) fn start(&self, now: Instant) -> Box<Source<S>> {
+        (self.start)(now)
+    }
+}
+
+/// A running subscription.
+pub(crate) trait Source<S> {
+    /// Returns the state produced by the handler for the next item that is available, if any.
+    fn next(&mut self, state: &S, now: Instant, notify: &NotifyHandle) -> Option<Result<S, InvocationError>>;
+
+    /// The moment at which the source will have an item, if it knows.
+    fn deadline(&self) -> Option<Instant>;
+}
+
+struct TimerSource<S> {
+    period: Duration,
+    repeat: bool,
+    due: Option<Instant>,
+    handler: Rc<SubscriptionHandler<S, Tick>>,
+}
+
+impl<S: State> Source<S> for TimerSource<S> {
+    fn next(&mut self, state: &S, now: Instant, _notify: &NotifyHandle) -> Option<Result<S, InvocationError>> {
+        match self.due {
+            Some(due) if due <= now => {
+                self.due = if self.repeat { Some(next_due(due, self.period, now)) } else { None };
+                Some((self.handler)(state.clone(), &Tick { now }))
+            },
+            _ => None
+        }
+    }
+
+    fn deadline(&self) -> Option<Instant> {
+        self.due
+    }
+}
+
+/// The first multiple of `period` after `due` that is still to come, so that ticks don't drift
+/// by the time it takes to handle them.
+fn next_due(due: Instant, period: Duration, now: Instant) -> Instant {
+    if period == Duration::from_secs(0) {
+        return now;
+    }
+
+    let mut next = due + period;
+    while next <= now {
+        next += period;
+    }
+    next
+}
+
+struct StreamSource<S, St: Stream> {
+    /// None once the stream has ended.
+    stream: Option<Spawn<St>>,
+    handler: Rc<SubscriptionHandler<S, St::Item>>,
+}
+
+impl<S: State, St: Stream> Source<S> for StreamSource<S, St> where St::Error: Debug {
+    fn next(&mut self, state: &S, _now: Instant, notify: &NotifyHandle) -> Option<Result<S, InvocationError>> {
+        let item = match self.stream {
+            Some(ref mut stream) => stream.poll_stream_notify(notify, 0),
+            None => return None
+        };
+
+        match item {
+            Ok(Async::Ready(Some(item))) => Some((self.handler)(state.clone(), &item)),
+            Ok(Async::NotReady) => None,
+            Ok(Async::Ready(None)) => {
+                self.stream = None;
+                None
+            },
+            Err(e) => {
+                warn!("Subscription stream failed: {:?}", e);
+                self.stream = None;
+                None
+            }
+        }
+    }
+
+    fn deadline(&self) -> Option<Instant> {
+        None
+    }
+}
+
+/// Used to poll streams when there is no event loop to wake up, e.g. in a headless application.
+/// Such applications poll their subscriptions themselves.
+struct NoopNotify;
+
+impl Notify for NoopNotify {
+    fn notify(&self, _id: usize) {}
+}
+
+pub(crate) fn noop_notify_handle() -> NotifyHandle {
+    Arc::new(NoopNotify).into()
+}
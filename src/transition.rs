@@ -0,0 +1,293 @@
+use webrender::api::{ColorF, LayoutRect};
+use animation::{seconds, Easing, Interpolate};
+use model::{Component, ComponentId, InspectableComponent, RenderContext, RenderElement};
+use layout::{FlexStyle, StyleUnit};
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// The properties of a component that can be transitioned. Style properties only transition
+/// between values in points, colors between the colors of the rectangles a component renders.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TransitionProperty {
+    Width,
+    Height,
+    MinWidth,
+    MinHeight,
+    MaxWidth,
+    MaxHeight,
+    Margin,
+    Padding,
+    Left,
+    Top,
+    Right,
+    Bottom,
+    Color,
+}
+
+/// Declares that changes to a property of a component should be animated instead of applied at
+/// once when the tree is rebuilt.
+#[derive(Clone, Debug)]
+pub struct Transition {
+    pub property: TransitionProperty,
+    pub duration: Duration,
+    pub easing: Easing,
+}
+
+impl Transition {
+    pub fn new(property: TransitionProperty, duration: Duration) -> Transition {
+        Transition {
+            property,
+            duration,
+            easing: Easing::EaseInOut,
+        }
+    }
+
+    pub fn easing(mut self, easing: Easing) -> Self {
+        self.easing = easing;
+        self
+    }
+}
+
+/// Components are matched between rebuilds by a step for every component on the way from the
+/// root: its name and its position among the siblings with the same name, or among the unnamed
+/// siblings. Named components keep their identity when differently named siblings come and go,
+/// while list items with the same name stay apart.
+type Identity = Vec<(Option<String>, usize)>;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Value {
+    Point(f32),
+    Color(ColorF),
+}
+
+impl Value {
+    fn interpolate(&self, to: &Value, t: f32) -> Value {
+        match (*self, *to) {
+            (Value::Point(from), Value::Point(to)) => Value::Point(from.interpolate(&to, t)),
+            (Value::Color(from), Value::Color(to)) => Value::Color(from.interpolate(&to, t)),
+            (_, to) => to
+        }
+    }
+}
+
+struct Running {
+    from: Value,
+    to: Value,
+    elapsed: Duration,
+    duration: Duration,
+    easing: Easing,
+}
+
+impl Running {
+    fn value(&self) -> Value {
+        if self.elapsed >= self.duration {
+            return self.to;
+        }
+
+        let t = seconds(self.elapsed) / seconds(self.duration);
+        self.from.interpolate(&self.to, self.easing.apply(t))
+    }
+}
+
+/// Keeps track of the transitioned properties in a tree that is rebuilt over time. The values in
+/// every new tree are compared to the ones seen in the previous tree, and a change starts a
+/// transition from the value that is currently shown.
+pub struct Transitions {
+    targets: HashMap<(Identity, TransitionProperty), Value>,
+    running: HashMap<(Identity, TransitionProperty), Running>,
+}
+
+impl Transitions {
+    pub fn new() -> Transitions {
+        Transitions {
+            targets: HashMap::new(),
+            running: HashMap::new(),
+        }
+    }
+
+    /// Compares the transitioned properties in a rebuilt tree to the previous one.
+    pub fn update(&mut self, root: &Component) {
+        let mut targets = HashMap::new();
+        collect_targets(root, &mut Vec::new(), &mut targets);
+
+        for (key, to) in &targets {
+            let transition = match self.targets.get(key) {
+                Some(previous) if previous != &to.0 => to.1.clone(),
+                _ => continue
+            };
+
+            let from = self.running.get(key).map_or_else(|| self.targets[key], |running| running.value());
+            self.running.insert(key.clone(), Running {
+                from,
+                to: to.0,
+                elapsed: Duration::from_millis(0),
+                duration: transition.duration,
+                easing: transition.easing,
+            });
+        }
+
+        // Components that are gone or stopped declaring a transition jump to their new values
+        self.running.retain(|key, _| targets.contains_key(key));
+        self.targets = targets.into_iter().map(|(key, (value, _))| (key, value)).collect();
+    }
+
+    pub fn advance(&mut self, elapsed: Duration) {
+        for running in self.running.values_mut() {
+            running.elapsed += elapsed;
+        }
+        self.running.retain(|_, running| running.elapsed < running.duration);
+    }
+
+    pub fn is_active(&self) -> bool {
+        !self.running.is_empty()
+    }
+
+    /// The styles and colors that currently differ from the ones in the tree, by the id the
+    /// components have in `root`.
+    pub fn overrides(&self, root: &Component) -> (HashMap<ComponentId, Vec<FlexStyle>>, HashMap<ComponentId, ColorF>) {
+        let mut styles = HashMap::new();
+        let mut colors = HashMap::new();
+
+        if self.is_active() {
+            self.collect_overrides(root, &mut Vec::new(), &mut styles, &mut colors);
+        }
+
+        (styles, colors)
+    }
+
+    fn collect_overrides(&self, node: &Component, identity: &mut Identity, styles: &mut HashMap<ComponentId, Vec<FlexStyle>>, colors: &mut HashMap<ComponentId, ColorF>) {
+        for transition in node.inspect().transitions() {
+            let running = match self.running.get(&(identity.clone(), transition.property)) {
+                Some(running) => running,
+                None => continue
+            };
+
+            match running.value() {
+                Value::Point(value) => {
+                    if let Some(style) = to_style(transition.property, value) {
+                        styles.entry(*node.inspect().id()).or_insert_with(Vec::new).push(style);
+                    }
+                },
+                Value::Color(color) => {
+                    colors.insert(*node.inspect().id(), color);
+                }
+            }
+        }
+
+        for (child, step) in node.inspect().children().iter().zip(child_steps(node)) {
+            identity.push(step);
+            self.collect_overrides(child, identity, styles, colors);
+            identity.pop();
+        }
+    }
+}
+
+/// The identity steps of the children of `node`, in order.
+fn child_steps(node: &Component) -> Vec<(Option<String>, usize)> {
+    let mut counts: HashMap<Option<String>, usize> = HashMap::new();
+    node.inspect().children().iter().map(|child| {
+        let name = child.inspect().name().clone();
+        let count = counts.entry(name.clone()).or_insert(0);
+        *count += 1;
+        (name, *count - 1)
+    }).collect()
+}
+
+fn collect_targets(node: &Component, identity: &mut Identity, targets: &mut HashMap<(Identity, TransitionProperty), (Value, Transition)>) {
+    for transition in node.inspect().transitions() {
+        let value = match transition.property {
+            TransitionProperty::Color => rendered_color(node).map(Value::Color),
+            property => node.inspect().styles().iter().rev().filter_map(|style| from_style(style, property)).next().map(Value::Point)
+        };
+
+        if let Some(value) = value {
+            targets.insert((identity.clone(), transition.property), (value, transition.clone()));
+        }
+    }
+
+    for (child, step) in node.inspect().children().iter().zip(child_steps(node)) {
+        identity.push(step);
+        collect_targets(child, identity, targets);
+        identity.pop();
+    }
+}
+
+fn from_style(style: &FlexStyle, property: TransitionProperty) -> Option<f32> {
+    let unit = match (style, property) {
+        (&FlexStyle::Width(ref unit), TransitionProperty::Width) |
+        (&FlexStyle::Height(ref unit), TransitionProperty::Height) |
+        (&FlexStyle::MinWidth(ref unit), TransitionProperty::MinWidth) |
+        (&FlexStyle::MinHeight(ref unit), TransitionProperty::MinHeight) |
+        (&FlexStyle::MaxWidth(ref unit), TransitionProperty::MaxWidth) |
+        (&FlexStyle::MaxHeight(ref unit), TransitionProperty::MaxHeight) |
+        (&FlexStyle::Margin(ref unit), TransitionProperty::Margin) |
+        (&FlexStyle::Padding(ref unit), TransitionProperty::Padding) |
+        (&FlexStyle::Left(ref unit), TransitionProperty::Left) |
+        (&FlexStyle::Top(ref unit), TransitionProperty::Top) |
+        (&FlexStyle::Right(ref unit), TransitionProperty::Right) |
+        (&FlexStyle::Bottom(ref unit), TransitionProperty::Bottom) => unit,
+        _ => return None
+    };
+
+    match *unit {
+        StyleUnit::Point(value) => Some(value.into_inner()),
+        _ => None
+    }
+}
+
+fn to_style(property: TransitionProperty, value: f32) -> Option<FlexStyle> {
+    let unit = StyleUnit::Point(value.into());
+    let style = match property {
+        TransitionProperty::Width => FlexStyle::Width(unit),
+        TransitionProperty::Height => FlexStyle::Height(unit),
+        TransitionProperty::MinWidth => FlexStyle::MinWidth(unit),
+        TransitionProperty::MinHeight => FlexStyle::MinHeight(unit),
+        TransitionProperty::MaxWidth => FlexStyle::MaxWidth(unit),
+        TransitionProperty::MaxHeight => FlexStyle::MaxHeight(unit),
+        TransitionProperty::Margin => FlexStyle::Margin(unit),
+        TransitionProperty::Padding => FlexStyle::Padding(unit),
+        TransitionProperty::Left => FlexStyle::Left(unit),
+        TransitionProperty::Top => FlexStyle::Top(unit),
+        TransitionProperty::Right => FlexStyle::Right(unit),
+        TransitionProperty::Bottom => FlexStyle::Bottom(unit),
+        TransitionProperty::Color => return None
+    };
+    Some(style)
+}
+
+/// The color of the first rectangle the component renders itself, without its children.
+fn rendered_color(node: &Component) -> Option<ColorF> {
+    let mut collector = ColorCollector {
+        component: node.inspect(),
+        color: None,
+    };
+    collector.render();
+    collector.color
+}
+
+struct ColorCollector<'a> {
+    component: &'a InspectableComponent,
+    color: Option<ColorF>,
+}
+
+impl<'a> RenderContext for ColorCollector<'a> {
+    fn render(&mut self) {
+        self.component.renderer().render(self);
+    }
+
+    fn push(&mut self, e: RenderElement) {
+        match e {
+            RenderElement::Rect(_, color) => {
+                if self.color.is_none() {
+                    self.color = Some(color);
+                }
+            }
+        }
+    }
+
+    fn next(&mut self) {}
+
+    fn bounds(&self) -> LayoutRect {
+        LayoutRect::zero()
+    }
+}
@@ -0,0 +1,94 @@
+use std::collections::VecDeque;
+
+/// How a state transition is recorded for undo, see `State::undo_step()`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum UndoStep {
+    /// The transition can be undone on its own.
+    Record,
+    /// The transition is undone together with the step before it.
+    Merge,
+    /// The transition can't be undone, e.g. because it only changes a selection or a scroll
+    /// position. Undoing the step before it also drops this change.
+    Ignore,
+}
+
+/// Snapshots of earlier states to go back to. Transitions that happen inside a transaction are
+/// undone as a single step.
+pub struct UndoStack<S> {
+    past: VecDeque<S>,
+    future: Vec<S>,
+    limit: usize,
+    transaction_depth: usize,
+    /// Whether the open transaction already recorded its step.
+    transaction_recorded: bool,
+}
+
+impl<S: Clone> UndoStack<S> {
+    /// Keeps at most `limit` steps to undo, forgetting the oldest ones first.
+    pub fn new(limit: usize) -> UndoStack<S> {
+        UndoStack {
+            past: VecDeque::new(),
+            future: Vec::new(),
+            limit,
+            transaction_depth: 0,
+            transaction_recorded: false,
+        }
+    }
+
+    /// Records a transition away from `previous`.
+    pub fn record(&mut self, previous: S, step: UndoStep) {
+        let merge = match step {
+            UndoStep::Ignore => return,
+            UndoStep::Merge => !self.past.is_empty(),
+            UndoStep::Record => self.transaction_depth > 0 && self.transaction_recorded
+        };
+
+        if !merge {
+            self.past.push_back(previous);
+            if self.past.len() > self.limit {
+                self.past.pop_front();
+            }
+            if self.transaction_depth > 0 {
+                self.transaction_recorded = true;
+            }
+        }
+        self.future.clear();
+    }
+
+    pub fn begin_transaction(&mut self) {
+        if self.transaction_depth == 0 {
+            self.transaction_recorded = false;
+        }
+        self.transaction_depth += 1;
+    }
+
+    pub fn end_transaction(&mut self) {
+        if self.transaction_depth > 0 {
+            self.transaction_depth -= 1;
+        }
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.past.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.future.is_empty()
+    }
+
+    /// Returns the state to go back to from `current`, if any.
+    pub fn undo(&mut self, current: S) -> Option<S> {
+        let previous = self.past.pop_back()?;
+        self.future.push(current);
+        self.transaction_recorded = false;
+        Some(previous)
+    }
+
+    /// Returns the state that was undone last, if any.
+    pub fn redo(&mut self, current: S) -> Option<S> {
+        let next = self.future.pop()?;
+        self.past.push_back(current);
+        self.transaction_recorded = false;
+        Some(next)
+    }
+}
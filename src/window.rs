@@ -4,34 +4,307 @@ use glutin::GlContext;
 use webrender;
 use webrender::api::*;
 use layout_context::LayoutContext;
-use futures::{Async, Poll, Stream};
+use futures::{Async, Future, Poll, Stream};
 use futures::task;
-use model::{Component, Event};
+use futures::executor::{Notify, NotifyHandle};
+use model::Component;
+use gesture::GestureConfig;
+use input::{Interaction, PointerState, TouchEvent};
 use std::rc::Rc;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::sync::{Arc, Mutex};
 use std::sync::mpsc;
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
+use std::thread;
+use std::time::{Duration, Instant};
+use tokio_core::reactor::{Handle, Timeout};
+
+/// How often `EventStream` looks for window events while it waits. Everything else wakes it up
+/// right away.
+const GLUTIN_POLL_INTERVAL_MS: u64 = 4;
 
 #[derive(Debug)]
 pub enum WindowEvent {
-    ApplicationClosed,
     WindowClosed,
     NotifyRenderComplete,
+    /// A deadline requested with `EventStream::wake_at` has passed.
+    Timer,
+    /// A future or stream polled with `EventStream::notify_handle` is ready to make progress.
+    Wakeup,
+    /// The new size of the window in logical pixels.
+    Resized(LayoutSize),
     Interaction(WorldPoint, Interaction),
+    Touch(TouchEvent),
     GlutinEvent(glutin::Event),
     GlutinWindowEvent(glutin::WindowEvent)
 }
 
-#[derive(Debug)]
-pub enum Interaction {
-    Pressed,
-    Released,
+/// Converts a size in device pixels, as glutin reports it, to logical pixels.
+pub fn logical_size(size: (u32, u32), device_pixel_ratio: f32) -> LayoutSize {
+    LayoutSize::new(size.0 as f32 / device_pixel_ratio, size.1 as f32 / device_pixel_ratio)
+}
+
+/// Converts a position in device pixels, as glutin reports it, to logical pixels.
+pub fn logical_point(position: (f64, f64), device_pixel_ratio: f32) -> WorldPoint {
+    WorldPoint::new(position.0 as f32 / device_pixel_ratio, position.1 as f32 / device_pixel_ratio)
+}
+
+/// Converts a size in logical pixels to whole device pixels.
+pub fn device_size(size: &LayoutSize, device_pixel_ratio: f32) -> (u32, u32) {
+    ((size.width * device_pixel_ratio).round() as u32, (size.height * device_pixel_ratio).round() as u32)
+}
+
+/// Limits a size in logical pixels to the given minimum and maximum.
+pub fn clamp_size(size: &LayoutSize, min_size: Option<LayoutSize>, max_size: Option<LayoutSize>) -> LayoutSize {
+    let mut size = *size;
+    if let Some(min_size) = min_size {
+        size.width = size.width.max(min_size.width);
+        size.height = size.height.max(min_size.height);
+    }
+    if let Some(max_size) = max_size {
+        size.width = size.width.min(max_size.width);
+        size.height = size.height.min(max_size.height);
+    }
+    size
+}
+
+/// Sent to the root component after the window has been resized and the tree has been laid out
+/// at the new size.
+#[derive(Clone, Debug)]
+pub struct WindowResized {
+    pub size: LayoutSize,
+}
+
+impl_describe_debug!(WindowResized);
+
+/// Settings that are passed on to webrender when the window is created.
+#[derive(Clone, Debug)]
+pub struct RendererOptions {
+    pub debug: bool,
+    pub precache_shaders: bool,
+    pub enable_aa: bool,
+    pub enable_subpixel_aa: bool,
+    pub resource_override_path: Option<PathBuf>,
+}
+
+impl Default for RendererOptions {
+    fn default() -> RendererOptions {
+        RendererOptions {
+            debug: false,
+            precache_shaders: true,
+            enable_aa: true,
+            enable_subpixel_aa: false,
+            resource_override_path: None,
+        }
+    }
+}
+
+/// The contents of a window in device pixels.
+#[derive(Clone, Debug)]
+pub struct Screenshot {
+    pub width: u32,
+    pub height: u32,
+    /// RGBA, 8 bits per channel, top row first.
+    pub pixels: Vec<u8>,
+}
+
+/// The default options have an empty title, see `Application::window`.
+#[derive(Clone, Debug)]
+pub struct WindowOptions {
+    pub title: String,
+    /// Initial inner size in logical pixels. The platform picks a size when not set.
+    pub size: Option<(u32, u32)>,
+    /// Initial position of the window on the screen. The platform picks one when not set.
+    pub position: Option<(i32, i32)>,
+    /// Minimum and maximum inner size in logical pixels.
+    pub min_size: Option<(u32, u32)>,
+    pub max_size: Option<(u32, u32)>,
+    /// A window that isn't resizable keeps its initial size, which is `size` or the size the
+    /// platform picked.
+    pub resizable: bool,
+    pub decorations: bool,
+    pub background_color: ColorF,
+    pub vsync: bool,
+    pub renderer: RendererOptions,
+}
+
+impl Default for WindowOptions {
+    fn default() -> WindowOptions {
+        WindowOptions::new("")
+    }
+}
+
+impl WindowOptions {
+    pub fn new<T: Into<String>>(title: T) -> WindowOptions {
+        WindowOptions {
+            title: title.into(),
+            size: None,
+            position: None,
+            min_size: None,
+            max_size: None,
+            resizable: true,
+            decorations: true,
+            background_color: ColorF::new(1.0, 1.0, 1.0, 1.0),
+            vsync: true,
+            renderer: RendererOptions::default(),
+        }
+    }
+
+    pub fn size(mut self, width: u32, height: u32) -> Self {
+        self.size = Some((width, height));
+        self
+    }
+
+    pub fn position(mut self, x: i32, y: i32) -> Self {
+        self.position = Some((x, y));
+        self
+    }
+
+    pub fn min_size(mut self, width: u32, height: u32) -> Self {
+        self.min_size = Some((width, height));
+        self
+    }
+
+    pub fn max_size(mut self, width: u32, height: u32) -> Self {
+        self.max_size = Some((width, height));
+        self
+    }
+
+    pub fn resizable(mut self, resizable: bool) -> Self {
+        self.resizable = resizable;
+        self
+    }
+
+    pub fn decorations(mut self, decorations: bool) -> Self {
+        self.decorations = decorations;
+        self
+    }
+
+    pub fn background_color(mut self, color: ColorF) -> Self {
+        self.background_color = color;
+        self
+    }
+
+    pub fn vsync(mut self, vsync: bool) -> Self {
+        self.vsync = vsync;
+        self
+    }
+
+    pub fn renderer(mut self, renderer: RendererOptions) -> Self {
+        self.renderer = renderer;
+        self
+    }
+}
+
+/// Window properties that follow the application state, see `State::window()`. Properties that
+/// are not set are left as they are.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct WindowProperties {
+    pub title: Option<String>,
+    /// Inner size in logical pixels.
+    pub size: Option<(u32, u32)>,
+    pub fullscreen: bool,
+}
+
+impl WindowProperties {
+    pub fn new() -> WindowProperties {
+        WindowProperties::default()
+    }
+
+    pub fn title<T: Into<String>>(mut self, title: T) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    pub fn size(mut self, width: u32, height: u32) -> Self {
+        self.size = Some((width, height));
+        self
+    }
+
+    pub fn fullscreen(mut self, fullscreen: bool) -> Self {
+        self.fullscreen = fullscreen;
+        self
+    }
+}
+
+/// Title, inner size in logical pixels and fullscreen mode of a window, each only where it is
+/// known or is to be changed.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct WindowPropertyChanges {
+    pub title: Option<String>,
+    pub size: Option<(u32, u32)>,
+    pub fullscreen: Option<bool>,
+}
+
+impl WindowPropertyChanges {
+    /// Takes over the values that are set in `changes`.
+    pub fn merge(&mut self, changes: &WindowPropertyChanges) {
+        if changes.title.is_some() {
+            self.title = changes.title.clone();
+        }
+        if changes.size.is_some() {
+            self.size = changes.size;
+        }
+        if changes.fullscreen.is_some() {
+            self.fullscreen = changes.fullscreen;
+        }
+    }
+}
+
+/// What has to change about a window whose known properties are `current` to give it the
+/// `wanted` ones. Unknown properties are always changed, and properties that aren't wanted are
+/// left alone.
+pub fn window_property_changes(wanted: &WindowProperties, current: &WindowPropertyChanges) -> WindowPropertyChanges {
+    WindowPropertyChanges {
+        title: wanted.title.clone().filter(|title| current.title.as_ref() != Some(title)),
+        size: wanted.size.filter(|&size| current.size != Some(size)),
+        fullscreen: Some(wanted.fullscreen).filter(|&fullscreen| current.fullscreen != Some(fullscreen)),
+    }
+}
+
+/// An additional window described by `State::windows()`, next to the main window. It is opened
+/// when its key first shows up and closed when the key is gone again.
+pub struct ChildWindow {
+    pub key: String,
+    pub options: WindowOptions,
+    pub properties: WindowProperties,
+    pub root: Component,
+}
+
+impl ChildWindow {
+    pub fn new<K: Into<String>>(key: K, root: Component) -> ChildWindow {
+        let key = key.into();
+
+        ChildWindow {
+            options: WindowOptions::new(key.clone()),
+            properties: WindowProperties::default(),
+            key,
+            root,
+        }
+    }
+
+    pub fn options(mut self, options: WindowOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    pub fn properties(mut self, properties: WindowProperties) -> Self {
+        self.properties = properties;
+        self
+    }
 }
 
-impl Event for Interaction {}
+/// Sent to the root component of the main window when the user closed a child window. The window
+/// stays closed until the state stops describing it and describes it again later.
+#[derive(Clone, Debug)]
+pub struct ChildWindowClosed {
+    pub key: String,
+}
 
-#[derive(Clone, Copy, PartialEq)]
+impl_describe_debug!(ChildWindowClosed);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Epoch(pub u32);
 
 impl Epoch {
@@ -42,37 +315,109 @@ impl Epoch {
     }
 }
 
+pub type WindowId = glutin::WindowId;
+
 struct Notifier {
-    window_events_tx: mpsc::Sender<WindowEvent>
+    window_id: WindowId,
+    wakeup: Wakeup,
 }
 
 impl RenderNotifier for Notifier {
     fn new_frame_ready(&mut self) {
         info!("new_frame_ready");
         #[cfg(not(target_os = "android"))]
-        self.window_events_tx.send(WindowEvent::NotifyRenderComplete).unwrap();
+        self.wakeup.send(Some(self.window_id), WindowEvent::NotifyRenderComplete);
     }
 
     fn new_scroll_frame_ready(&mut self, _composite_needed: bool) {
         info!("new_scroll_frame_ready");
         #[cfg(not(target_os = "android"))]
-        self.window_events_tx.send(WindowEvent::NotifyRenderComplete).unwrap();
+        self.wakeup.send(Some(self.window_id), WindowEvent::NotifyRenderComplete);
     }
 }
 
 pub struct RendererHandle {
     epoch: Epoch,
+    /// Epoch of the last frame that was sent to webrender.
+    submitted_epoch: Option<Epoch>,
+    /// Epoch of the last frame that made it to the screen.
+    presented_epoch: Option<Epoch>,
     layout_context: Rc<RefCell<LayoutContext>>,
     tree: Option<Arc<Mutex<Component>>>,
     window_size: (u32, u32),
+    device_pixel_ratio: Rc<Cell<f32>>,
+    /// Size limits in logical pixels, enforced whenever the window is resized.
+    min_size: Option<LayoutSize>,
+    max_size: Option<LayoutSize>,
+    background_color: ColorF,
     gl_window: glutin::GlWindow,
+    gl: Rc<gl::Gl>,
     renderer: webrender::renderer::Renderer,
     api: webrender::api::RenderApi,
     document_id: webrender::api::DocumentId,
 }
 
 impl RendererHandle {
+    pub fn id(&self) -> WindowId {
+        self.gl_window.id()
+    }
+
+    /// The size of the window in logical pixels, which is what the layout works with.
+    pub fn layout_size(&self) -> LayoutSize {
+        logical_size(self.window_size, self.device_pixel_ratio.get())
+    }
+
+    /// The position of the window on the screen, if the platform knows it.
+    pub fn position(&self) -> Option<(i32, i32)> {
+        self.gl_window.get_position()
+    }
+
+    pub fn device_pixel_ratio(&self) -> f32 {
+        self.device_pixel_ratio.get()
+    }
+
+    /// Picks up the current size and DPI factor of the window, e.g. after it was resized or moved
+    /// to another monitor. Returns whether anything changed.
+    pub fn update_window_size(&mut self) -> bool {
+        let (width, height) = self.gl_window.get_inner_size_pixels().unwrap_or(self.window_size);
+        let device_pixel_ratio = self.gl_window.hidpi_factor();
+        let (width, height) = limit_size(&self.gl_window, (width, height), device_pixel_ratio, self.min_size, self.max_size);
+        if (width, height) == self.window_size && device_pixel_ratio == self.device_pixel_ratio.get() {
+            return false;
+        }
+
+        self.window_size = (width, height);
+        self.device_pixel_ratio.set(device_pixel_ratio);
+        self.gl_window.resize(width, height);
+        true
+    }
+
+    /// Applies the properties that are set, see `window_property_changes`.
+    pub fn apply_window_properties(&mut self, changes: &WindowPropertyChanges) {
+        if let Some(ref title) = changes.title {
+            self.gl_window.set_title(title);
+        }
+
+        if let Some((width, height)) = changes.size {
+            let (width, height) = device_size(&LayoutSize::new(width as f32, height as f32), self.device_pixel_ratio.get());
+            self.gl_window.set_inner_size(width, height);
+        }
+
+        if let Some(fullscreen) = changes.fullscreen {
+            let monitor = if fullscreen {
+                Some(self.gl_window.get_current_monitor())
+            } else {
+                None
+            };
+            self.gl_window.set_fullscreen(monitor);
+        }
+    }
+
+    /// Presents the frame webrender has finished, which blocks until the next vsync if it is
+    /// enabled.
     pub fn update(&mut self) {
+        self.presented_epoch = self.submitted_epoch;
+        self.make_current();
         self.renderer.update();
         self.renderer.render(DeviceUintSize::new(self.window_size.0, self.window_size.1));
         self.gl_window.swap_buffers().unwrap();
@@ -81,32 +426,99 @@ impl RendererHandle {
     pub fn render(&mut self) {
         if let Some(ref tree) = self.tree {
             info!("render()");
-            let layout_size = LayoutSize::new(self.window_size.0 as f32, self.window_size.1 as f32);
+            let device_size = DeviceUintSize::new(self.window_size.0, self.window_size.1);
+
+            let epoch = self.epoch.next();
+            generate_frame(&self.api, &self.document_id, &device_size, self.device_pixel_ratio.get(), &self.background_color, &epoch, &self.layout_context.borrow(), &tree.lock().unwrap());
+            self.submitted_epoch = Some(epoch);
+        }
+    }
+
+    /// Whether a submitted frame has not been presented yet. Rendering again before that would
+    /// only produce a frame that is never shown.
+    pub fn frame_pending(&self) -> bool {
+        // The notifier doesn't report finished frames on Android
+        cfg!(not(target_os = "android")) && self.submitted_epoch != self.presented_epoch
+    }
 
-            generate_frame(&self.api, &self.document_id, &layout_size, &self.epoch.next(), &mut self.layout_context.borrow_mut(), &tree.lock().unwrap());
-            //context.rendered_epoch = context.epoch;
+    pub fn submitted_epoch(&self) -> Option<Epoch> {
+        self.submitted_epoch
+    }
+
+    pub fn presented_epoch(&self) -> Option<Epoch> {
+        self.presented_epoch
+    }
+
+    /// Captures the last frame webrender finished, which may lag behind the latest changes.
+    pub fn screenshot(&mut self) -> Screenshot {
+        let (width, height) = self.window_size;
+        self.make_current();
+        // The back buffer is undefined after a swap, so draw the frame again without presenting it
+        self.renderer.render(DeviceUintSize::new(width, height));
+        let pixels = self.gl.read_pixels(0, 0, width as gl::GLsizei, height as gl::GLsizei, gl::RGBA, gl::UNSIGNED_BYTE);
+
+        // GL returns the bottom row first
+        let stride = width as usize * 4;
+        let mut flipped = Vec::with_capacity(pixels.len());
+        for row in pixels.chunks(stride).rev() {
+            flipped.extend_from_slice(row);
+        }
+
+        Screenshot {
+            width,
+            height,
+            pixels: flipped,
         }
     }
 
     pub fn set_tree(&mut self, tree: Arc<Mutex<Component>>) {
         self.tree = Some(tree);
     }
+
+    /// Tears down the renderer and closes the window.
+    pub fn close(self) {
+        self.make_current();
+        self.renderer.deinit();
+    }
+
+    fn make_current(&self) {
+        // Every window has its own GL context
+        unsafe {
+            let _ = self.gl_window.make_current();
+        }
+    }
 }
 
 pub struct WebrenderWindow;
 
 impl WebrenderWindow {
-    pub fn new(title: &'static str, layout_context: Rc<RefCell<LayoutContext>>) -> (RendererHandle, EventStream) {
-        let (window_events_tx, window_events_rx) = mpsc::channel::<WindowEvent>();
-
-        let window = glutin::WindowBuilder::new()
-            .with_title(title)
+    /// Opens a window on the events loop of `event_stream`, which will from then on also produce
+    /// the events of this window.
+    pub fn new(options: &WindowOptions, layout_context: Rc<RefCell<LayoutContext>>, event_stream: &mut EventStream) -> RendererHandle {
+        let mut window = glutin::WindowBuilder::new()
+            .with_title(options.title.clone())
+            .with_decorations(options.decorations)
             .with_multitouch();
 
-        let glutin_events = glutin::EventsLoop::new();
+        if let Some((width, height)) = options.size {
+            window = window.with_dimensions(width, height);
+        }
+
+        let context = glutin::ContextBuilder::new().with_vsync(options.vsync);
+        let gl_window = glutin::GlWindow::new(window, context, &event_stream.glutin_events).unwrap();
 
-        let context = glutin::ContextBuilder::new().with_vsync(false);
-        let gl_window = glutin::GlWindow::new(window, context, &glutin_events).unwrap();
+        // The initial size is given in logical pixels, but the DPI factor is only known now
+        if let Some((width, height)) = options.size {
+            let factor = gl_window.hidpi_factor();
+            if factor != 1.0 {
+                let (width, height) = device_size(&LayoutSize::new(width as f32, height as f32), factor);
+                gl_window.set_inner_size(width, height);
+            }
+        }
+
+        if let Some((x, y)) = options.position {
+            gl_window.set_position(x, y);
+        }
 
         unsafe {
             let _ = gl_window.make_current().unwrap();
@@ -122,134 +534,361 @@ impl WebrenderWindow {
         };
 
         //println!("OpenGL version {}", gl.get_string(gl::VERSION));
-        //println!("Shader resource path: {:?}", res_path);
 
         let (width, height) = gl_window.get_inner_size_pixels().unwrap();
+        let factor = gl_window.hidpi_factor();
+
+        // Glutin only takes size limits in device pixels before the DPI factor is known, and
+        // can't make a window non-resizable, so the limits are enforced on every resize instead
+        let to_size = |(width, height): (u32, u32)| LayoutSize::new(width as f32, height as f32);
+        let (min_size, max_size) = if options.resizable {
+            (options.min_size.map(&to_size), options.max_size.map(&to_size))
+        } else {
+            let size = options.size.map(&to_size).unwrap_or_else(|| logical_size((width, height), factor));
+            (Some(size), Some(size))
+        };
+        let (width, height) = limit_size(&gl_window, (width, height), factor, min_size, max_size);
+        let device_pixel_ratio = Rc::new(Cell::new(factor));
 
         let opts = webrender::RendererOptions {
-            //resource_override_path: res_path,
-            debug: true,
-            precache_shaders: true,
+            resource_override_path: options.renderer.resource_override_path.clone(),
+            debug: options.renderer.debug,
+            precache_shaders: options.renderer.precache_shaders,
+            enable_aa: options.renderer.enable_aa,
+            enable_subpixel_aa: options.renderer.enable_subpixel_aa,
+            // The display list is built in device pixels, see LayoutContext::build_display_list(),
+            // so that the ratio can change when the window moves to another monitor.
             device_pixel_ratio: 1.0,
-            //window.hidpi_factor(),
             ..webrender::RendererOptions::default()
         };
 
-        let (renderer, sender) = webrender::renderer::Renderer::new(gl, opts).unwrap();
+        let (renderer, sender) = webrender::renderer::Renderer::new(gl.clone(), opts).unwrap();
 
         let api = sender.create_api();
         let document_id = api.add_document(DeviceUintSize::zero());
         api.set_root_pipeline(document_id, PipelineId(0, 0));
 
         let notifier = Box::new(Notifier {
-            window_events_tx: window_events_tx.clone()
+            window_id: gl_window.id(),
+            wakeup: event_stream.wakeup.clone(),
         });
         renderer.set_render_notifier(notifier);
 
-        (RendererHandle {
+        event_stream.windows.insert(gl_window.id(), WindowInput {
+            pointer: PointerState::new(event_stream.gestures.clone()),
+            device_pixel_ratio: device_pixel_ratio.clone(),
+        });
+
+        RendererHandle {
             epoch: Epoch(0),
+            submitted_epoch: None,
+            presented_epoch: None,
             layout_context,
             tree: None,
             window_size: (width, height),
+            device_pixel_ratio,
+            min_size,
+            max_size,
+            background_color: options.background_color,
             gl_window,
+            gl,
             renderer,
             api,
             document_id,
-        }, EventStream {
-            glutin_events,
-            window_events: window_events_rx,
-            events: VecDeque::new(),
-            mouse: WorldPoint::zero()
-        })
+        }
+    }
+}
+
+/// Resizes the window when its size is outside of the limits, and returns the size it ends up with
+/// in device pixels.
+fn limit_size(gl_window: &glutin::GlWindow, size: (u32, u32), device_pixel_ratio: f32, min_size: Option<LayoutSize>, max_size: Option<LayoutSize>) -> (u32, u32) {
+    let logical = logical_size(size, device_pixel_ratio);
+    let limited = clamp_size(&logical, min_size, max_size);
+    if limited == logical {
+        return size;
+    }
+
+    let (width, height) = device_size(&limited, device_pixel_ratio);
+    gl_window.set_inner_size(width, height);
+    (width, height)
+}
+
+/// Input state that is tracked per window.
+struct WindowInput {
+    pointer: PointerState,
+    device_pixel_ratio: Rc<Cell<f32>>,
+}
+
+/// Wakes up the task that polls `EventStream` when an event was sent from outside of it.
+#[derive(Clone)]
+struct Wakeup {
+    window_events_tx: Arc<Mutex<mpsc::Sender<(Option<WindowId>, WindowEvent)>>>,
+    task: Arc<Mutex<Option<task::Task>>>,
+}
+
+impl Wakeup {
+    fn send(&self, window_id: Option<WindowId>, event: WindowEvent) -> bool {
+        if self.window_events_tx.lock().unwrap().send((window_id, event)).is_err() {
+            return false;
+        }
+
+        // Nobody is waiting yet before the stream was first polled
+        if let Some(ref task) = *self.task.lock().unwrap() {
+            task.notify();
+        }
+        true
+    }
+}
+
+/// Lets futures and streams that are polled by the application wake it up when they're ready.
+impl Notify for Wakeup {
+    fn notify(&self, _id: usize) {
+        self.send(None, WindowEvent::Wakeup);
+    }
+}
+
+/// Calls `fire` on a thread of its own whenever the earliest requested deadline has passed. The
+/// thread stops once `fire` returns false or the timer is dropped. `EventStream` uses it to
+/// produce `WindowEvent::Timer`.
+pub struct Timer {
+    deadlines_tx: mpsc::Sender<Instant>,
+}
+
+impl Timer {
+    pub fn new<F: Fn() -> bool + Send + 'static>(fire: F) -> Timer {
+        let (deadlines_tx, deadlines_rx) = mpsc::channel::<Instant>();
+
+        thread::spawn(move || {
+            let mut deadlines: Vec<Instant> = Vec::new();
+            loop {
+                let received = match deadlines.iter().min().cloned() {
+                    Some(deadline) => {
+                        let now = Instant::now();
+                        if deadline <= now {
+                            deadlines.retain(|d| *d > now);
+                            if !fire() {
+                                return;
+                            }
+                            continue;
+                        }
+
+                        match deadlines_rx.recv_timeout(deadline - now) {
+                            Ok(deadline) => Some(deadline),
+                            Err(mpsc::RecvTimeoutError::Timeout) => None,
+                            Err(mpsc::RecvTimeoutError::Disconnected) => return
+                        }
+                    },
+                    None => match deadlines_rx.recv() {
+                        Ok(deadline) => Some(deadline),
+                        Err(_) => return
+                    }
+                };
+
+                if let Some(deadline) = received {
+                    deadlines.push(deadline);
+                }
+            }
+        });
+
+        Timer { deadlines_tx }
+    }
+
+    /// Deadlines that have already passed fire right away.
+    pub fn wake_at(&self, deadline: Instant) {
+        // The thread is only gone once `fire` asked it to stop
+        let _ = self.deadlines_tx.send(deadline);
     }
 }
 
+/// Produces the events of all windows opened on it, together with the id of the window they
+/// belong to. Events that don't belong to a window, such as `GlutinEvent`s, have no id.
+///
+/// When no events are pending, polling returns `NotReady` so the reactor can run other futures.
+/// The stream is woken up when a renderer finishes a frame, a deadline passed to `wake_at` is
+/// reached or something polled with `notify_handle` is ready. Glutin events are picked up within
+/// a few milliseconds.
 pub struct EventStream {
     glutin_events: glutin::EventsLoop,
-    window_events: mpsc::Receiver<WindowEvent>,
-    events: VecDeque<WindowEvent>,
-    mouse: WorldPoint,
+    wakeup: Wakeup,
+    timer: Timer,
+    reactor: Handle,
+    /// Polls glutin again while nothing else wakes up the stream.
+    glutin_timeout: Option<Timeout>,
+    window_events: mpsc::Receiver<(Option<WindowId>, WindowEvent)>,
+    events: VecDeque<(Option<WindowId>, WindowEvent)>,
+    windows: HashMap<WindowId, WindowInput>,
+    gestures: GestureConfig,
 }
 
-impl Stream for EventStream {
-    type Item = WindowEvent;
-    type Error = ();
+impl EventStream {
+    /// Click counts of pointer events are based on the double-click thresholds of `gestures`. The
+    /// stream has to be polled on the core of `reactor`, which it never blocks, so that the other
+    /// futures on it keep making progress.
+    pub fn new(gestures: GestureConfig, reactor: &Handle) -> EventStream {
+        let (window_events_tx, window_events_rx) = mpsc::channel();
+        let wakeup = Wakeup {
+            window_events_tx: Arc::new(Mutex::new(window_events_tx)),
+            task: Arc::new(Mutex::new(None)),
+        };
 
-    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
-        let mut polled_events = Vec::new();
+        EventStream {
+            glutin_events: glutin::EventsLoop::new(),
+            timer: {
+                let wakeup = wakeup.clone();
+                Timer::new(move || wakeup.send(None, WindowEvent::Timer))
+            },
+            wakeup,
+            reactor: reactor.clone(),
+            glutin_timeout: None,
+            window_events: window_events_rx,
+            events: VecDeque::new(),
+            windows: HashMap::new(),
+            gestures,
+        }
+    }
 
-        // Grab all Glutin events
-        let mut mouse: WorldPoint = self.mouse;
-        self.glutin_events.poll_events(|event| {
-            let weld_event = match event {
-                glutin::Event::WindowEvent { event, .. } => match event {
-                    glutin::WindowEvent::Closed => WindowEvent::WindowClosed,
-                    glutin::WindowEvent::MouseMoved { position: (x, y), .. } => {
-                        mouse = WorldPoint::new(x as f32, y as f32);
-                        WindowEvent::GlutinWindowEvent(event)
-                    },
-                    glutin::WindowEvent::MouseInput { button: glutin::MouseButton::Left, state: glutin::ElementState::Pressed, .. } => {
-                        WindowEvent::Interaction(mouse, Interaction::Pressed)
-                    },
-                    glutin::WindowEvent::MouseInput { button: glutin::MouseButton::Left, state: glutin::ElementState::Released, .. } => {
-                        WindowEvent::Interaction(mouse, Interaction::Released)
-                    },
-                    _ => WindowEvent::GlutinWindowEvent(event)
-                },
-                _ => WindowEvent::GlutinEvent(event)
-            };
+    /// Stops tracking input for a window that has been closed.
+    pub fn forget(&mut self, window_id: &WindowId) {
+        self.windows.remove(window_id);
+    }
+
+    /// A handle for polling futures and streams so that the stream produces a
+    /// `WindowEvent::Wakeup` when they're ready to make progress.
+    pub fn notify_handle(&self) -> NotifyHandle {
+        Arc::new(self.wakeup.clone()).into()
+    }
+
+    /// Makes the stream produce a `WindowEvent::Timer` once `deadline` has passed.
+    pub fn wake_at(&self, deadline: Instant) {
+        self.timer.wake_at(deadline);
+    }
 
-            polled_events.push(weld_event);
+    /// Picks up the events that are available right now without blocking, and returns whether
+    /// any are waiting to be polled.
+    pub fn has_pending(&mut self) -> bool {
+        self.poll_glutin_events();
+        self.poll_window_events();
+        !self.events.is_empty()
+    }
+
+    fn poll_glutin_events(&mut self) {
+        let windows = &mut self.windows;
+        let events = &mut self.events;
+        self.glutin_events.poll_events(|event| {
+            events.extend(translate_event(windows, event));
         });
-        self.mouse = mouse;
-        self.events.extend(polled_events);
+    }
+
+    /// Glutin can't wake up the reactor, so its events are picked up by polling again shortly.
+    fn schedule_glutin_poll(&mut self) -> Result<(), ()> {
+        let mut timeout = Timeout::new(Duration::from_millis(GLUTIN_POLL_INTERVAL_MS), &self.reactor).map_err(|e| {
+            warn!("Could not schedule polling for window events: {}", e);
+        })?;
+        match timeout.poll() {
+            Ok(Async::NotReady) => self.glutin_timeout = Some(timeout),
+            _ => task::current().notify()
+        }
+        Ok(())
+    }
 
-        // Grab all events sent by notifier
+    fn poll_window_events(&mut self) {
         loop {
             match self.window_events.try_recv() {
                 Ok(event) => self.events.push_back(event),
                 Err(_) => break
             }
         }
+    }
+}
+
+fn translate_event(windows: &mut HashMap<WindowId, WindowInput>, event: glutin::Event) -> Option<(Option<WindowId>, WindowEvent)> {
+    match event {
+        glutin::Event::WindowEvent { window_id, event } => {
+            let input = match windows.get_mut(&window_id) {
+                Some(input) => input,
+                None => return None
+            };
+            let pointer = &mut input.pointer;
+            let device_pixel_ratio = input.device_pixel_ratio.get();
+
+            let weld_event = match event {
+                glutin::WindowEvent::Closed => WindowEvent::WindowClosed,
+                glutin::WindowEvent::Resized(width, height) => {
+                    WindowEvent::Resized(logical_size((width, height), device_pixel_ratio))
+                },
+                glutin::WindowEvent::MouseMoved { position, .. } => {
+                    let interaction = pointer.moved(logical_point(position, device_pixel_ratio));
+                    WindowEvent::Interaction(pointer.position, interaction)
+                },
+                glutin::WindowEvent::MouseInput { button, state: glutin::ElementState::Pressed, .. } => {
+                    let interaction = pointer.pressed(button.into());
+                    WindowEvent::Interaction(pointer.position, interaction)
+                },
+                glutin::WindowEvent::MouseInput { button, state: glutin::ElementState::Released, .. } => {
+                    let interaction = pointer.released(button.into());
+                    WindowEvent::Interaction(pointer.position, interaction)
+                },
+                glutin::WindowEvent::MouseWheel { delta, .. } => {
+                    let interaction = pointer.wheel(delta.into());
+                    WindowEvent::Interaction(pointer.position, interaction)
+                },
+                glutin::WindowEvent::Touch(touch) => WindowEvent::Touch(TouchEvent::from_glutin(touch, device_pixel_ratio)),
+                glutin::WindowEvent::KeyboardInput { input, .. } => {
+                    pointer.modifiers.update(&input);
+                    WindowEvent::GlutinWindowEvent(event)
+                },
+                _ => WindowEvent::GlutinWindowEvent(event)
+            };
+
+            Some((Some(window_id), weld_event))
+        },
+        // Only used to interrupt run_forever(), the event that caused it is in the channel
+        glutin::Event::Awakened => None,
+        _ => Some((None, WindowEvent::GlutinEvent(event)))
+    }
+}
+
+impl Stream for EventStream {
+    type Item = (Option<WindowId>, WindowEvent);
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        // Registered before looking for events, so that an event sent in between still wakes us up
+        *self.wakeup.task.lock().unwrap() = Some(task::current());
+
+        self.poll_glutin_events();
+        self.poll_window_events();
 
         // Publish in stream
         match self.events.pop_front() {
-            Some(event) => {
-                match event {
-                    WindowEvent::WindowClosed => Ok(Async::Ready(None)),
-                    _ => Ok(Async::Ready(Some(event)))
-                }
-            },
+            Some(event) => Ok(Async::Ready(Some(event))),
             None => {
-                // No messages were polled, notify the task so we will be re-polled in a little while
-                let t = task::current();
-                t.notify();
-
+                // Nothing to do, the reactor goes on with other futures until the wakeups, the
+                // timer or the next glutin poll have something for us
+                self.schedule_glutin_poll()?;
                 Ok(Async::NotReady)
             }
         }
     }
 }
 
-fn generate_frame(api: &RenderApi, document_id: &DocumentId, layout_size: &LayoutSize, epoch: &Epoch, layout_context: &mut LayoutContext, tree: &Component) {
+/// The tree has to be laid out already, see `AppWindow::ensure_layout`.
+fn generate_frame(api: &RenderApi, document_id: &DocumentId, device_size: &DeviceUintSize, device_pixel_ratio: f32, background_color: &ColorF, epoch: &Epoch, layout_context: &LayoutContext, tree: &Component) {
     info!("generate_frame, epoch: {}", epoch.0);
-    let device_size = DeviceUintSize::new(layout_size.width as u32, layout_size.height as u32);
-    let root_background_color = ColorF::new(0.0, 0.7, 0.0, 1.0);
-    api.set_window_parameters(*document_id, device_size, DeviceUintRect::new(DeviceUintPoint::zero(), device_size));
+    let viewport_size = LayoutSize::new(device_size.width as f32, device_size.height as f32);
+    api.set_window_parameters(*document_id, *device_size, DeviceUintRect::new(DeviceUintPoint::zero(), *device_size));
     api.set_display_list(*document_id,
                          webrender::api::Epoch(epoch.0),
-                         Some(root_background_color),
-                         *layout_size,
-                         build_display_list(&layout_size, layout_context, tree).finalize(),
+                         Some(*background_color),
+                         viewport_size,
+                         build_display_list(&viewport_size, device_pixel_ratio, layout_context, tree).finalize(),
                          true);
     api.generate_frame(*document_id, None);
 }
 
-fn build_display_list(layout_size: &LayoutSize, layout_context: &mut LayoutContext, tree: &Component) -> DisplayListBuilder {
-    let mut builder = DisplayListBuilder::new(PipelineId(0, 0), *layout_size);
-
-    layout_context.update_layout(&tree, layout_size);
-    layout_context.build_display_list(&mut builder, &tree);
+fn build_display_list(viewport_size: &LayoutSize, device_pixel_ratio: f32, layout_context: &LayoutContext, tree: &Component) -> DisplayListBuilder {
+    let mut builder = DisplayListBuilder::new(PipelineId(0, 0), *viewport_size);
+    layout_context.build_display_list(&mut builder, &tree, device_pixel_ratio);
 
     builder
-}
\ No newline at end of file
+}
@@ -0,0 +1,115 @@
+extern crate weld;
+extern crate webrender;
+
+use weld::animation::*;
+use weld::application::Application;
+use weld::model::*;
+use std::time::Duration;
+
+#[path = "common/utils.rs"]
+#[macro_use]
+#[allow(dead_code)]
+mod utils;
+
+#[derive(Debug)]
+struct Container {}
+
+impl_dummy_renderer!(Container);
+
+#[test]
+fn test_easing() {
+    for easing in &[Easing::Linear, Easing::EaseIn, Easing::EaseOut, Easing::EaseInOut, Easing::CubicBezier(0.25, 0.1, 0.25, 1.0)] {
+        assert_eq!(easing.apply(0.0), 0.0);
+        assert!((easing.apply(1.0) - 1.0).abs() < 1e-4);
+    }
+
+    assert!(Easing::EaseIn.apply(0.5) < 0.5);
+    assert!(Easing::EaseOut.apply(0.5) > 0.5);
+    assert!((Easing::CubicBezier(0.0, 0.0, 1.0, 1.0).apply(0.3) - 0.3).abs() < 1e-4);
+}
+
+#[test]
+fn test_timeline() {
+    let mut timeline = Timeline::new(0.0)
+        .then(100.0, Duration::from_millis(100), Easing::Linear)
+        .delay(Duration::from_millis(50))
+        .then(0.0, Duration::from_millis(100), Easing::Linear);
+    assert_eq!(timeline.duration(), Duration::from_millis(250));
+
+    timeline.advance(Duration::from_millis(50));
+    assert_eq!(timeline.value(), 50.0);
+    timeline.advance(Duration::from_millis(75));
+    assert_eq!(timeline.value(), 100.0);
+    timeline.advance(Duration::from_millis(75));
+    assert_eq!(timeline.value(), 50.0);
+    assert!(timeline.is_active());
+
+    timeline.advance(Duration::from_millis(100));
+    assert_eq!(timeline.value(), 0.0);
+    assert!(!timeline.is_active());
+}
+
+#[test]
+fn test_spring() {
+    let mut spring = Spring::new(0.0);
+    assert!(!spring.is_active());
+
+    spring.set_target(10.0);
+    spring.advance(Duration::from_millis(100));
+    assert!(spring.value > 0.0 && spring.value < 10.0);
+
+    spring.advance(Duration::from_secs(5));
+    assert!(!spring.is_active());
+    assert_eq!(spring.value, 10.0);
+}
+
+#[test]
+fn test_spring_short_frames() {
+    let mut once = Spring::new(0.0);
+    once.set_target(10.0);
+    once.advance(Duration::from_millis(10));
+
+    // Frames shorter than an integration step add up instead of being rounded away
+    let mut often = Spring::new(0.0);
+    often.set_target(10.0);
+    for _ in 0..25 {
+        often.advance(Duration::from_micros(400));
+    }
+    assert!(often.value > 0.0);
+    assert_eq!(often.value, once.value);
+}
+
+#[derive(Clone, Debug)]
+struct FadeState {
+    opacity: Timeline<f32>,
+}
+
+impl State for FadeState {
+    fn build(&self) -> Component {
+        Component::new(Container {})
+            .on(Box::new(|mut state: FadeState, event: &AnimationFrame| {
+                state.opacity.advance(event.elapsed);
+                Ok(state)
+            }))
+    }
+
+    fn animating(&self) -> bool {
+        self.opacity.is_active()
+    }
+}
+
+#[test]
+fn test_virtual_clock() {
+    let mut app = Application::new("animation", FadeState {
+        opacity: Timeline::tween(0.0, 1.0, Duration::from_millis(200), Easing::Linear),
+    });
+    assert!(app.state().animating());
+
+    app.advance(Duration::from_millis(50));
+    app.advance(Duration::from_millis(50));
+    assert_eq!(app.state().opacity.value(), 0.5);
+
+    app.advance(Duration::from_millis(150));
+    assert_eq!(app.state().opacity.value(), 1.0);
+    assert!(!app.state().animating());
+}
@@ -0,0 +1,296 @@
+extern crate weld;
+extern crate webrender;
+
+use weld::application::{Application, CloseRequested};
+use weld::model::*;
+use weld::input::{Interaction, Modifiers, MouseButton, PointerEvent, TouchEvent, TouchPhase};
+use weld::window::{ChildWindow, WindowEvent, WindowProperties, WindowResized};
+use weld::layout::{Percent, Point};
+use weld::layout::FlexStyle::*;
+use webrender::api::{LayoutPoint, LayoutSize, WorldPoint};
+use std::time::Instant;
+
+#[path = "common/utils.rs"]
+#[macro_use]
+#[allow(dead_code)]
+mod utils;
+
+#[derive(Debug)]
+struct Container {}
+
+impl_dummy_renderer!(Container);
+
+#[derive(Clone, Debug)]
+struct WindowState {
+    size: Option<LayoutSize>,
+}
+
+impl State for WindowState {
+    fn build(&self) -> Component {
+        Component::new(Container {})
+            .styles(vec![Width(100.percent()), Height(50.percent())])
+            .on(Box::new(|_state: WindowState, event: &WindowResized| {
+                Ok(WindowState { size: Some(event.size) })
+            }))
+    }
+}
+
+#[test]
+fn test_resize() {
+    let mut app = Application::new("resize", WindowState { size: None });
+    app.resize(LayoutSize::new(100.0, 100.0));
+
+    app.inject(WindowEvent::Resized(LayoutSize::new(640.0, 480.0)));
+    assert_eq!(app.state().size, Some(LayoutSize::new(640.0, 480.0)));
+
+    let tree = app.tree();
+    let bounds = app.layout_context().borrow().get_bounds(tree.inspect());
+    assert_eq!(bounds.size, LayoutSize::new(640.0, 240.0));
+}
+
+#[test]
+fn test_updates_coalesce_into_one_frame() {
+    let mut app = Application::new("coalesce", WindowState { size: None });
+    app.resize(LayoutSize::new(100.0, 100.0));
+    app.render();
+    let passes = app.layout_context().borrow().layout_passes();
+    let frames = app.frames();
+
+    let dispatcher = app.dispatcher();
+    for width in 1..4 {
+        dispatcher.post_update(move |_| WindowState { size: Some(LayoutSize::new(width as f32, 0.0)) });
+    }
+    app.poll_dispatched();
+    app.render();
+    assert_eq!(app.state().size, Some(LayoutSize::new(3.0, 0.0)));
+    assert_eq!(app.layout_context().borrow().layout_passes(), passes + 1);
+    assert_eq!(app.frames(), frames + 1);
+
+    // Nothing changed since the last frame
+    app.render();
+    assert_eq!(app.layout_context().borrow().layout_passes(), passes + 1);
+    assert_eq!(app.frames(), frames + 1);
+}
+
+#[derive(Clone, Debug)]
+struct WindowsState {
+    palette: bool,
+    palette_touches: u32,
+}
+
+fn started(event: &TouchEvent) -> bool {
+    event.phase == TouchPhase::Started
+}
+
+impl State for WindowsState {
+    fn build(&self) -> Component {
+        Component::new(Container {})
+            .styles(vec![Width(100.percent()), Height(100.percent())])
+            .on(Box::new(|state: WindowsState, event: &TouchEvent| {
+                if started(event) {
+                    Ok(WindowsState { palette: !state.palette, ..state })
+                } else {
+                    Err(InvocationError)
+                }
+            }))
+    }
+
+    fn windows(&self) -> Vec<ChildWindow> {
+        if !self.palette {
+            return Vec::new();
+        }
+
+        let palette = Component::new(Container {})
+            .styles(vec![Width(100.percent()), Height(100.percent())])
+            .on(Box::new(|state: WindowsState, event: &TouchEvent| {
+                if started(event) {
+                    Ok(WindowsState { palette_touches: state.palette_touches + 1, ..state })
+                } else {
+                    Err(InvocationError)
+                }
+            }));
+
+        vec![ChildWindow::new("palette", palette)]
+    }
+}
+
+fn touch_started(id: u64) -> WindowEvent {
+    WindowEvent::Touch(TouchEvent {
+        id,
+        phase: TouchPhase::Started,
+        window_position: WorldPoint::new(5.0, 5.0),
+        local_position: LayoutPoint::new(5.0, 5.0),
+        timestamp: Instant::now(),
+    })
+}
+
+#[test]
+fn test_child_windows() {
+    let mut app = Application::new("windows", WindowsState { palette: false, palette_touches: 0 });
+    app.resize(LayoutSize::new(100.0, 100.0));
+    assert!(app.child_windows().is_empty());
+
+    app.inject(touch_started(1));
+    assert_eq!(app.child_windows(), vec!["palette"]);
+
+    app.inject_into("palette", WindowEvent::Resized(LayoutSize::new(50.0, 50.0)));
+    app.inject_into("palette", touch_started(2));
+    assert_eq!(app.state().palette_touches, 1);
+
+    app.inject(touch_started(3));
+    assert!(app.child_windows().is_empty());
+    assert_eq!(app.state().palette_touches, 1);
+}
+
+#[derive(Clone, Debug)]
+struct EditorState {
+    unsaved: bool,
+    asked: bool,
+    exit: Option<i32>,
+}
+
+impl State for EditorState {
+    fn build(&self) -> Component {
+        Component::new(Container {})
+            .on(Box::new(|state: EditorState, _event: &CloseRequested| {
+                if state.unsaved {
+                    Ok(EditorState { asked: true, ..state })
+                } else {
+                    Ok(EditorState { exit: Some(3), ..state })
+                }
+            }))
+    }
+
+    fn exit_status(&self) -> Option<i32> {
+        self.exit
+    }
+}
+
+#[test]
+fn test_close_veto() {
+    let mut app = Application::new("close", EditorState { unsaved: true, asked: false, exit: None });
+
+    app.inject(WindowEvent::WindowClosed);
+    assert!(app.state().asked);
+    assert_eq!(app.exit_status(), None);
+
+    app.dispatcher().post_update(|state: EditorState| EditorState { unsaved: false, ..state });
+    app.poll_dispatched();
+    app.request_close();
+    assert_eq!(app.exit_status(), Some(3));
+}
+
+#[derive(Clone, Debug)]
+struct SliderState {
+    received: Vec<(&'static str, Option<MouseButton>)>,
+}
+
+impl State for SliderState {
+    fn build(&self) -> Component {
+        Component::new(Container {})
+            .styles(vec![Width(100.point()), Height(100.point())])
+            .child(
+                Component::new(Container {})
+                    .styles(vec![Width(50.point()), Height(50.point())])
+                    .capture_pointer(true)
+                    .on(Box::new(|state: SliderState, event: &Interaction| {
+                        let received = match *event {
+                            Interaction::Pressed(ref e) => ("pressed", e.button),
+                            Interaction::Moved(ref e) => ("moved", e.button),
+                            Interaction::Released(ref e) => ("released", e.button),
+                            Interaction::Wheel(_) => return Err(InvocationError)
+                        };
+                        let mut state = state;
+                        state.received.push(received);
+                        Ok(state)
+                    }))
+            )
+    }
+}
+
+fn pointer(x: f32, y: f32, button: Option<MouseButton>) -> PointerEvent {
+    PointerEvent {
+        button,
+        click_count: 1,
+        modifiers: Modifiers::none(),
+        window_position: WorldPoint::new(x, y),
+        local_position: LayoutPoint::new(x, y),
+        timestamp: Instant::now(),
+    }
+}
+
+fn interact(app: &mut Application<SliderState>, interaction: Interaction) {
+    app.inject(WindowEvent::Interaction(interaction.window_position(), interaction));
+}
+
+#[test]
+fn test_pointer_capture() {
+    let mut app = Application::new("capture", SliderState { received: Vec::new() });
+    app.resize(LayoutSize::new(100.0, 100.0));
+
+    interact(&mut app, Interaction::Pressed(pointer(10.0, 10.0, Some(MouseButton::Left))));
+    interact(&mut app, Interaction::Moved(pointer(80.0, 80.0, Some(MouseButton::Left))));
+    interact(&mut app, Interaction::Released(pointer(80.0, 80.0, Some(MouseButton::Left))));
+    interact(&mut app, Interaction::Moved(pointer(90.0, 90.0, None)));
+
+    assert_eq!(app.state().received, vec![
+        ("pressed", Some(MouseButton::Left)),
+        ("moved", Some(MouseButton::Left)),
+        ("released", Some(MouseButton::Left)),
+    ]);
+}
+
+#[test]
+fn test_pointer_capture_with_second_button() {
+    let mut app = Application::new("capture", SliderState { received: Vec::new() });
+    app.resize(LayoutSize::new(100.0, 100.0));
+
+    interact(&mut app, Interaction::Pressed(pointer(10.0, 10.0, Some(MouseButton::Left))));
+    // The second button is pressed outside of the slider, but released while it is captured
+    interact(&mut app, Interaction::Pressed(pointer(80.0, 80.0, Some(MouseButton::Right))));
+    interact(&mut app, Interaction::Released(pointer(80.0, 80.0, Some(MouseButton::Right))));
+    interact(&mut app, Interaction::Moved(pointer(90.0, 90.0, Some(MouseButton::Left))));
+    interact(&mut app, Interaction::Released(pointer(90.0, 90.0, Some(MouseButton::Left))));
+    interact(&mut app, Interaction::Moved(pointer(95.0, 95.0, None)));
+
+    assert_eq!(app.state().received, vec![
+        ("pressed", Some(MouseButton::Left)),
+        ("released", Some(MouseButton::Right)),
+        ("moved", Some(MouseButton::Left)),
+        ("released", Some(MouseButton::Left)),
+    ]);
+}
+
+#[derive(Clone, Debug)]
+struct TitleState {
+    count: u32,
+}
+
+impl State for TitleState {
+    fn build(&self) -> Component {
+        Component::new(Container {})
+            .on(Box::new(|state: TitleState, _event: &WindowResized| {
+                Ok(TitleState { count: state.count + 1 })
+            }))
+    }
+
+    fn window(&self) -> WindowProperties {
+        WindowProperties::new().title(format!("Count {}", self.count)).size(800, 600)
+    }
+}
+
+#[test]
+fn test_window_properties() {
+    let mut app = Application::new("properties", TitleState { count: 0 });
+    app.resize(LayoutSize::new(800.0, 600.0));
+
+    let changes = app.window_property_changes(None).unwrap();
+    assert_eq!(changes.title, Some("Count 0".to_string()));
+    assert_eq!(changes.size, None);
+
+    // A size the user changed by hand is applied again with the next state update
+    app.inject(WindowEvent::Resized(LayoutSize::new(1000.0, 700.0)));
+    let changes = app.window_property_changes(None).unwrap();
+    assert_eq!(changes.title, Some("Count 1".to_string()));
+    assert_eq!(changes.size, Some((800, 600)));
+}
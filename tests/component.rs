@@ -117,3 +117,35 @@ fn test_rendering() {
         ("child2".into(), "ComA".into()),
     ]);
 }
+
+#[test]
+fn test_seeded_ids() {
+    let build = || com_a().child(com_b()).child(com_b());
+    let ids = |component: &Component| -> Vec<ComponentId> {
+        let mut ids = vec![*component.inspect().id()];
+        ids.extend(component.inspect().children().iter().map(|child| *child.inspect().id()));
+        ids
+    };
+
+    let unseeded = ids(&build());
+    let first = Component::build_seeded(100, || ids(&build()));
+    let (repeated, nested) = Component::build_seeded(100, || {
+        let before = ids(&build());
+        // A nested build starts its own sequence, and the outer one goes on afterwards
+        let nested = Component::build_seeded(100, || ids(&build()));
+        let after = ids(&build());
+        assert_eq!(format!("{}", after[0]), "s103");
+        (before, nested)
+    });
+    let after_seeding = ids(&build());
+
+    // Builds with the same seed read the same, but their ids are still unique
+    let text = |ids: &Vec<ComponentId>| ids.iter().map(|id| id.to_string()).collect::<Vec<_>>();
+    assert_eq!(text(&first), vec!["s100", "s101", "s102"]);
+    assert_eq!(text(&repeated), text(&first));
+    assert_eq!(text(&nested), text(&first));
+    for other in &[&repeated, &nested, &unseeded, &after_seeding] {
+        assert!(first.iter().all(|id| !other.contains(id)));
+    }
+    assert!(!after_seeding[0].to_string().starts_with('s'));
+}
@@ -0,0 +1,61 @@
+extern crate weld;
+extern crate webrender;
+
+use weld::application::Application;
+use weld::model::*;
+use std::thread;
+
+#[path = "common/utils.rs"]
+#[macro_use]
+#[allow(dead_code)]
+mod utils;
+
+#[derive(Debug)]
+struct Container {}
+
+impl_dummy_renderer!(Container);
+
+#[derive(Debug)]
+struct WorkDone {
+    result: u32,
+}
+
+impl Event for WorkDone {}
+
+#[derive(Clone, Debug)]
+struct WorkerState {
+    results: Vec<u32>,
+}
+
+impl State for WorkerState {
+    fn build(&self) -> Component {
+        Component::new(Container {})
+            .on(Box::new(|mut state: WorkerState, event: &WorkDone| {
+                state.results.push(event.result);
+                Ok(state)
+            }))
+    }
+}
+
+#[test]
+fn test_dispatch_from_threads() {
+    let mut app = Application::new("dispatcher", WorkerState { results: Vec::new() });
+
+    let workers: Vec<_> = (0..4).map(|n| {
+        let dispatcher = app.dispatcher();
+        thread::spawn(move || dispatcher.post_event(WorkDone { result: n }))
+    }).collect();
+    for worker in workers {
+        worker.join().unwrap();
+    }
+
+    let dispatcher = app.dispatcher();
+    thread::spawn(move || dispatcher.post_update(|mut state: WorkerState| {
+        state.results.sort();
+        state
+    })).join().unwrap();
+
+    assert!(app.state().results.is_empty());
+    app.poll_dispatched();
+    assert_eq!(app.state().results, vec![0, 1, 2, 3]);
+}
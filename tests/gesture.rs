@@ -0,0 +1,157 @@
+extern crate weld;
+extern crate webrender;
+
+use weld::gesture::*;
+use weld::input::*;
+use webrender::api::{LayoutPoint, WorldPoint};
+use std::time::{Duration, Instant};
+
+fn pointer(x: f32, y: f32, timestamp: Instant) -> PointerEvent {
+    PointerEvent {
+        button: Some(MouseButton::Left),
+        click_count: 1,
+        modifiers: Modifiers::none(),
+        window_position: WorldPoint::new(x, y),
+        local_position: LayoutPoint::new(x, y),
+        timestamp,
+    }
+}
+
+fn names(gestures: &[(Vec<usize>, Gesture)]) -> Vec<(Vec<usize>, &'static str)> {
+    gestures.iter().map(|&(ref target, ref gesture)| {
+        let name = match *gesture {
+            Gesture::Click(_) => "click",
+            Gesture::DoubleClick(_) => "double-click",
+            Gesture::LongPress(_) => "long-press",
+            Gesture::DragStart(_) => "drag-start",
+            Gesture::DragMove(_) => "drag-move",
+            Gesture::DragEnd(_) => "drag-end",
+            Gesture::Pinch(_) => "pinch",
+            Gesture::Rotate(_) => "rotate",
+        };
+        (target.clone(), name)
+    }).collect()
+}
+
+#[test]
+fn test_click_and_double_click() {
+    let mut recognizer = GestureRecognizer::new(GestureConfig::default());
+    let target = vec![0, 1];
+    let t = Instant::now();
+
+    assert!(recognizer.handle(Some(&target), &Interaction::Pressed(pointer(1.0, 1.0, t))).is_empty());
+    let gestures = recognizer.handle(Some(&target), &Interaction::Released(pointer(1.0, 1.0, t + Duration::from_millis(50))));
+    assert_eq!(names(&gestures), vec![(target.clone(), "click")]);
+
+    recognizer.handle(Some(&target), &Interaction::Pressed(pointer(1.0, 1.0, t + Duration::from_millis(100))));
+    let gestures = recognizer.handle(Some(&target), &Interaction::Released(pointer(1.0, 1.0, t + Duration::from_millis(150))));
+    assert_eq!(names(&gestures), vec![(target.clone(), "click"), (target.clone(), "double-click")]);
+}
+
+#[test]
+fn test_release_on_other_component() {
+    let mut recognizer = GestureRecognizer::new(GestureConfig::default());
+    let t = Instant::now();
+
+    recognizer.handle(Some(&vec![0]), &Interaction::Pressed(pointer(1.0, 1.0, t)));
+    let gestures = recognizer.handle(Some(&vec![1]), &Interaction::Released(pointer(2.0, 2.0, t)));
+    assert!(gestures.is_empty());
+}
+
+#[test]
+fn test_long_press() {
+    let config = GestureConfig::default();
+    let mut recognizer = GestureRecognizer::new(config.clone());
+    let target = vec![0];
+    let t = Instant::now();
+
+    recognizer.handle(Some(&target), &Interaction::Pressed(pointer(1.0, 1.0, t)));
+    assert_eq!(recognizer.next_deadline(), Some(t + config.long_press_duration));
+    assert!(recognizer.tick(t + Duration::from_millis(10)).is_empty());
+    assert_eq!(names(&recognizer.tick(t + config.long_press_duration)), vec![(target.clone(), "long-press")]);
+
+    let gestures = recognizer.handle(Some(&target), &Interaction::Released(pointer(1.0, 1.0, t + config.long_press_duration * 2)));
+    assert!(gestures.is_empty());
+}
+
+#[test]
+fn test_drag() {
+    let mut recognizer = GestureRecognizer::new(GestureConfig { drag_threshold: 5.0, ..GestureConfig::default() });
+    let target = vec![0];
+    let t = Instant::now();
+
+    recognizer.handle(Some(&target), &Interaction::Pressed(pointer(10.0, 10.0, t)));
+    assert!(recognizer.handle(Some(&target), &Interaction::Moved(pointer(12.0, 10.0, t))).is_empty());
+    let start = recognizer.handle(Some(&target), &Interaction::Moved(pointer(20.0, 10.0, t)));
+    let moved = recognizer.handle(None, &Interaction::Moved(pointer(200.0, 10.0, t)));
+    let end = recognizer.handle(None, &Interaction::Released(pointer(200.0, 10.0, t)));
+
+    assert_eq!(names(&start), vec![(target.clone(), "drag-start")]);
+    assert_eq!(names(&moved), vec![(target.clone(), "drag-move")]);
+    assert_eq!(names(&end), vec![(target.clone(), "drag-end")]);
+    match end[0].1 {
+        Gesture::DragEnd(ref e) => assert_eq!(e.start_position, WorldPoint::new(10.0, 10.0)),
+        _ => unreachable!()
+    }
+}
+
+fn touch(id: u64, phase: TouchPhase, x: f32, y: f32) -> TouchEvent {
+    TouchEvent {
+        id,
+        phase,
+        window_position: WorldPoint::new(x, y),
+        local_position: LayoutPoint::new(x, y),
+        timestamp: Instant::now(),
+    }
+}
+
+#[test]
+fn test_rotate() {
+    let mut recognizer = GestureRecognizer::new(GestureConfig::default());
+    let target = vec![0];
+
+    recognizer.handle_touch(Some(&target), &touch(1, TouchPhase::Started, 10.0, 10.0));
+    recognizer.handle_touch(None, &touch(2, TouchPhase::Started, 30.0, 10.0));
+    // A quarter turn clockwise around the first finger, at the same distance
+    let gestures = recognizer.handle_touch(None, &touch(2, TouchPhase::Moved, 10.0, 30.0));
+    assert_eq!(names(&gestures), vec![(target.clone(), "pinch"), (target.clone(), "rotate")]);
+
+    match gestures[1].1.clone().localize(&LayoutPoint::new(5.0, 5.0)) {
+        Gesture::Rotate(e) => {
+            assert!((e.rotation - std::f32::consts::FRAC_PI_2).abs() < 1e-5);
+            assert_eq!(e.window_center, WorldPoint::new(10.0, 20.0));
+            assert_eq!(e.local_center, LayoutPoint::new(5.0, 15.0));
+        },
+        _ => panic!("expected a rotation")
+    }
+
+    recognizer.handle_touch(None, &touch(2, TouchPhase::Ended, 10.0, 30.0));
+    assert!(recognizer.handle_touch(None, &touch(1, TouchPhase::Moved, 20.0, 20.0)).is_empty());
+}
+
+fn rotation(gestures: &[(Vec<usize>, Gesture)]) -> f32 {
+    match gestures.last() {
+        Some(&(_, Gesture::Rotate(ref e))) => e.rotation,
+        _ => panic!("expected a rotation")
+    }
+}
+
+#[test]
+fn test_rotate_across_half_turn() {
+    let mut recognizer = GestureRecognizer::new(GestureConfig::default());
+    let target = vec![0];
+    let offset = (0.1f32).atan();
+
+    // The second finger starts left of the first one, just below the ±π boundary of the angle
+    recognizer.handle_touch(Some(&target), &touch(1, TouchPhase::Started, 50.0, 50.0));
+    recognizer.handle_touch(None, &touch(2, TouchPhase::Started, 40.0, 51.0));
+
+    // Crossing the boundary is a small step
+    let gestures = recognizer.handle_touch(None, &touch(2, TouchPhase::Moved, 40.0, 49.0));
+    assert!((rotation(&gestures) - 2.0 * offset).abs() < 1e-5);
+
+    // Rotations go on past half a turn
+    recognizer.handle_touch(None, &touch(2, TouchPhase::Moved, 50.0, 40.0));
+    let gestures = recognizer.handle_touch(None, &touch(2, TouchPhase::Moved, 60.0, 50.0));
+    assert!((rotation(&gestures) - (std::f32::consts::PI + offset)).abs() < 1e-5);
+}
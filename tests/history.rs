@@ -0,0 +1,90 @@
+#[macro_use]
+extern crate weld;
+extern crate webrender;
+
+use weld::application::Application;
+use weld::model::*;
+
+#[path = "common/utils.rs"]
+#[macro_use]
+#[allow(dead_code)]
+mod utils;
+
+#[derive(Debug)]
+struct Container {}
+
+impl_dummy_renderer!(Container);
+
+#[derive(Debug)]
+struct Increment(u32);
+
+impl_describe_debug!(Increment);
+
+struct Reset;
+
+impl Event for Reset {}
+
+#[derive(Clone, Debug)]
+struct CounterState {
+    count: u32,
+}
+
+impl State for CounterState {
+    fn build(&self) -> Component {
+        Component::new(Container {})
+            .name("counter")
+            .on(Box::new(|state: CounterState, event: &Increment| {
+                Ok(CounterState { count: state.count + event.0 })
+            }))
+            .on(Box::new(|_state: CounterState, _event: &Reset| {
+                Ok(CounterState { count: 0 })
+            }))
+    }
+}
+
+fn increment(app: &mut Application<CounterState>, by: u32) {
+    app.dispatcher().post_event(Increment(by));
+    app.poll_dispatched();
+}
+
+#[test]
+fn test_time_travel() {
+    let mut app = Application::new("history", CounterState { count: 0 }).record_history(true);
+    increment(&mut app, 1);
+    increment(&mut app, 2);
+    increment(&mut app, 3);
+    assert_eq!(app.history().unwrap().entries().len(), 4);
+
+    assert!(app.step_back());
+    assert!(app.step_back());
+    assert_eq!(app.state().count, 1);
+    assert!(app.step_forward());
+    assert_eq!(app.state().count, 3);
+    assert!(app.jump_to(0));
+    assert!(!app.step_back());
+    assert_eq!(app.state().count, 0);
+
+    // Continuing from an earlier state discards the states after it
+    increment(&mut app, 10);
+    assert_eq!(app.state().count, 10);
+    assert_eq!(app.history().unwrap().entries().len(), 2);
+    assert!(!app.step_forward());
+
+    let cause = app.history().unwrap().entries()[1].cause.clone().unwrap();
+    assert!(cause.event_type.ends_with("Increment"));
+    assert_eq!(cause.event, "Increment(10)");
+    assert_eq!(cause.component.unwrap().name, Some("counter".to_string()));
+
+    let mut export = Vec::new();
+    app.history().unwrap().export(&mut export).unwrap();
+    let export = String::from_utf8(export).unwrap();
+    assert!(export.contains("event: Increment(10)"));
+    assert!(export.contains("state: CounterState { count: 10 }"));
+
+    // Events that don't describe themselves are known by their type name
+    app.dispatcher().post_event(Reset);
+    app.poll_dispatched();
+    let cause = app.history().unwrap().entries()[2].cause.clone().unwrap();
+    assert!(cause.event.ends_with("Reset"));
+    assert_eq!(cause.event, cause.event_type);
+}
@@ -0,0 +1,130 @@
+extern crate weld;
+extern crate webrender;
+
+use weld::model::*;
+use weld::input::*;
+use weld::gesture::GestureConfig;
+use weld::layout_context::LayoutContext;
+use weld::layout::Point;
+use weld::layout::FlexStyle::*;
+use webrender::api::{LayoutPoint, LayoutSize, WorldPoint};
+use std::time::Instant;
+
+#[path = "common/utils.rs"]
+#[macro_use]
+#[allow(dead_code)]
+mod utils;
+
+#[derive(Debug)]
+struct Container {}
+
+impl_dummy_renderer!(Container);
+
+fn tree() -> Component {
+    Component::new(Container {})
+        .name("parent")
+        .styles(vec![Width(100.point()), Height(100.point()), Padding(10.point())])
+        .child(
+            Component::new(Container {})
+                .name("child")
+                .styles(vec![Width(20.point()), Height(20.point())])
+        )
+}
+
+fn pressed_at(x: f32, y: f32) -> Interaction {
+    Interaction::Pressed(PointerEvent {
+        button: Some(MouseButton::Right),
+        click_count: 1,
+        modifiers: Modifiers { ctrl: true, ..Modifiers::none() },
+        window_position: WorldPoint::new(x, y),
+        local_position: LayoutPoint::new(x, y),
+        timestamp: Instant::now(),
+    })
+}
+
+#[test]
+fn test_local_coordinates() {
+    let tree = tree();
+    let mut layout_context = LayoutContext::new();
+    layout_context.update_layout(&tree, &LayoutSize::new(100.0, 100.0));
+
+    let interaction = pressed_at(15.0, 17.0);
+    let node = layout_context.find_node_at(interaction.window_position(), &tree).unwrap();
+    assert_eq!(node.inspect().name(), &Some("child".to_string()));
+
+    let origin = layout_context.absolute_bounds(node.inspect()).origin;
+    match interaction.localize(&origin) {
+        Interaction::Pressed(e) => {
+            assert_eq!(e.local_position, LayoutPoint::new(5.0, 7.0));
+            assert_eq!(e.window_position, WorldPoint::new(15.0, 17.0));
+            assert_eq!(e.button, Some(MouseButton::Right));
+            assert!(e.modifiers.ctrl);
+        },
+        _ => panic!("unexpected interaction")
+    }
+}
+
+#[test]
+fn test_nested_local_coordinates() {
+    let tree = Component::new(Container {})
+        .styles(vec![Width(100.point()), Height(100.point()), Padding(10.point())])
+        .child(
+            Component::new(Container {})
+                .styles(vec![Width(50.point()), Height(50.point()), Padding(5.point())])
+                .child(
+                    Component::new(Container {})
+                        .name("inner")
+                        .styles(vec![Width(20.point()), Height(20.point())])
+                )
+        );
+    let mut layout_context = LayoutContext::new();
+    layout_context.update_layout(&tree, &LayoutSize::new(100.0, 100.0));
+
+    // The inner component is at (5, 5) in its parent, which is at (10, 10) in the window
+    let interaction = pressed_at(30.0, 32.0);
+    let node = layout_context.find_node_at(interaction.window_position(), &tree).unwrap();
+    assert_eq!(node.inspect().name(), &Some("inner".to_string()));
+    assert_eq!(layout_context.get_bounds(node.inspect()).origin, LayoutPoint::new(5.0, 5.0));
+
+    let origin = layout_context.absolute_bounds(node.inspect()).origin;
+    assert_eq!(origin, LayoutPoint::new(15.0, 15.0));
+    match interaction.localize(&origin) {
+        Interaction::Pressed(e) => assert_eq!(e.local_position, LayoutPoint::new(15.0, 17.0)),
+        _ => panic!("unexpected interaction")
+    }
+}
+
+#[test]
+fn test_click_count() {
+    let mut pointer = PointerState::new(GestureConfig::default());
+    pointer.moved(WorldPoint::new(5.0, 5.0));
+
+    let counts: Vec<u32> = (0..3).map(|_| {
+        let count = match pointer.pressed(MouseButton::Left) {
+            Interaction::Pressed(e) => e.click_count,
+            _ => unreachable!()
+        };
+        pointer.released(MouseButton::Left);
+        count
+    }).collect();
+    assert_eq!(counts, vec![1, 2, 3]);
+
+    match pointer.pressed(MouseButton::Middle) {
+        Interaction::Pressed(e) => assert_eq!(e.click_count, 1),
+        _ => unreachable!()
+    }
+}
+
+#[test]
+fn test_click_count_follows_gesture_config() {
+    let mut pointer = PointerState::new(GestureConfig { double_click_distance: 2.0, ..GestureConfig::default() });
+    pointer.moved(WorldPoint::new(5.0, 5.0));
+    pointer.pressed(MouseButton::Left);
+    pointer.released(MouseButton::Left);
+
+    pointer.moved(WorldPoint::new(8.0, 5.0));
+    match pointer.pressed(MouseButton::Left) {
+        Interaction::Pressed(e) => assert_eq!(e.click_count, 1),
+        _ => unreachable!()
+    }
+}
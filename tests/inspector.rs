@@ -0,0 +1,161 @@
+extern crate weld;
+extern crate webrender;
+extern crate log;
+
+use weld::application::Application;
+use weld::input::{Interaction, Modifiers, PointerEvent};
+use weld::inspector::{BoxModel, Edges};
+use weld::model::*;
+use weld::recording::Recording;
+use weld::window::WindowEvent;
+use weld::layout::{Percent, Point};
+use weld::layout::FlexStyle::*;
+use webrender::api::{LayoutPoint, LayoutSize, WorldPoint};
+use log::{Log, LogLevel, LogLevelFilter, LogMetadata, LogRecord};
+use std::cell::RefCell;
+use std::sync::{Once, ONCE_INIT};
+use std::time::Instant;
+
+#[path = "common/utils.rs"]
+#[macro_use]
+#[allow(dead_code)]
+mod utils;
+
+#[derive(Debug)]
+struct Container {}
+
+#[derive(Debug)]
+struct Button {}
+
+impl_dummy_renderer!(Container);
+impl_dummy_renderer!(Button);
+
+#[derive(Clone, Debug)]
+struct PanelState {
+    title: &'static str,
+}
+
+impl State for PanelState {
+    fn build(&self) -> Component {
+        Component::new(Container {})
+            .name("panel")
+            .styles(vec![Width(100.percent()), Height(100.percent())])
+            .child(
+                Component::new(Button {})
+                    .name("button")
+                    .styles(vec![Width(40.point()), Height(20.point()), Margin(10.point()), PaddingLeft(5.percent())])
+                    .on(Box::new(|state: PanelState, _event: &Interaction| Ok(state)))
+            )
+    }
+}
+
+thread_local!(static LOGGED: RefCell<Vec<String>> = RefCell::new(Vec::new()));
+
+/// Keeps the messages logged by each test thread, so that the tests can run in parallel.
+struct CaptureLogger;
+
+impl Log for CaptureLogger {
+    fn enabled(&self, metadata: &LogMetadata) -> bool {
+        metadata.level() <= LogLevel::Info
+    }
+
+    fn log(&self, record: &LogRecord) {
+        LOGGED.with(|logged| logged.borrow_mut().push(record.args().to_string()));
+    }
+}
+
+fn take_logs() -> Vec<String> {
+    static INIT: Once = ONCE_INIT;
+    INIT.call_once(|| {
+        log::set_logger(|max_level| {
+            max_level.set(LogLevelFilter::Info);
+            Box::new(CaptureLogger)
+        }).unwrap();
+    });
+    LOGGED.with(|logged| logged.borrow_mut().drain(..).collect())
+}
+
+fn inspector_logs() -> Vec<String> {
+    take_logs().into_iter().filter(|message| message.starts_with("Inspector:")).collect()
+}
+
+/// Keyboard events can only be created by glutin, so they are fed in as a recording.
+fn key(app: &mut Application<PanelState>, key: &str, state: &str, modifiers: &str) {
+    let text = format!("weld-recording 1\n0 - key 0 {} {} {}\n", state, key, modifiers);
+    Recording::read(text.as_bytes()).unwrap().replay(app);
+}
+
+fn move_to(x: f32, y: f32) -> WindowEvent {
+    WindowEvent::Interaction(WorldPoint::new(x, y), Interaction::Moved(PointerEvent {
+        button: None,
+        click_count: 0,
+        modifiers: Modifiers::none(),
+        window_position: WorldPoint::new(x, y),
+        local_position: LayoutPoint::new(x, y),
+        timestamp: Instant::now(),
+    }))
+}
+
+#[test]
+fn test_inspector() {
+    let mut app = Application::new("inspector", PanelState { title: "Settings" }).inspector();
+    app.resize(LayoutSize::new(200.0, 100.0));
+    assert_eq!(app.inspector_text(), None);
+
+    app.toggle_inspector();
+    app.inject(move_to(20.0, 20.0));
+
+    let text = app.inspector_text().unwrap();
+    assert!(text.starts_with("  Container \"panel\"\n>   Button \"button\"\n"), "{}", text);
+    assert!(text.contains("events: [weld::input::Interaction]"), "{}", text);
+    assert!(text.contains("title: \"Settings\""), "{}", text);
+
+    let tree = app.tree();
+    let box_model = BoxModel::new(tree.find_by_name("button").unwrap().inspect(), 200.0, &app.layout_context().borrow());
+    assert_eq!(box_model.margin, Edges { left: 10.0, top: 10.0, right: 10.0, bottom: 10.0 });
+    assert_eq!(box_model.padding, Edges { left: 10.0, top: 0.0, right: 0.0, bottom: 0.0 });
+    assert_eq!(box_model.margin_box().size, LayoutSize::new(60.0, 40.0));
+}
+
+#[test]
+fn test_inspector_logs_on_show() {
+    let mut app = Application::new("inspector", PanelState { title: "Settings" }).inspector();
+    app.resize(LayoutSize::new(200.0, 100.0));
+    take_logs();
+
+    app.toggle_inspector();
+    assert_eq!(inspector_logs().len(), 1);
+
+    // Hovering only updates the overlay, and hiding doesn't log
+    app.inject(move_to(20.0, 20.0));
+    app.toggle_inspector();
+    assert!(inspector_logs().is_empty());
+
+    app.toggle_inspector();
+    assert_eq!(inspector_logs().len(), 1);
+}
+
+#[test]
+fn test_inspector_shortcuts() {
+    let mut app = Application::new("inspector", PanelState { title: "Settings" }).inspector();
+    app.resize(LayoutSize::new(200.0, 100.0));
+    take_logs();
+
+    key(&mut app, "F12", "pressed", "-");
+    assert!(app.inspector_text().is_some());
+    assert_eq!(inspector_logs().len(), 1);
+
+    // Shift+F12 logs the hovered component again instead of hiding the inspector
+    app.inject(move_to(20.0, 20.0));
+    key(&mut app, "LShift", "pressed", "-");
+    key(&mut app, "F12", "pressed", "s");
+    let logs = inspector_logs();
+    assert_eq!(logs.len(), 1);
+    assert!(logs[0].contains(">   Button \"button\""), "{}", logs[0]);
+    assert!(app.inspector_text().is_some());
+
+    key(&mut app, "LShift", "released", "s");
+    key(&mut app, "F12", "pressed", "-");
+    assert_eq!(app.inspector_text(), None);
+    assert!(inspector_logs().is_empty());
+}
@@ -0,0 +1,111 @@
+#[macro_use]
+extern crate weld;
+extern crate webrender;
+
+use weld::application::Application;
+use weld::middleware::StateChange;
+use weld::model::*;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+#[path = "common/utils.rs"]
+#[macro_use]
+#[allow(dead_code)]
+mod utils;
+
+#[derive(Debug)]
+struct Container {}
+
+impl_dummy_renderer!(Container);
+
+#[derive(Debug)]
+struct SetVolume(i32);
+
+impl_describe_debug!(SetVolume);
+
+#[derive(Clone, Debug)]
+struct PlayerState {
+    volume: i32,
+    locked: bool,
+}
+
+impl State for PlayerState {
+    fn build(&self) -> Component {
+        Component::new(Container {})
+            .name("player")
+            .on(Box::new(|state: PlayerState, event: &SetVolume| {
+                Ok(PlayerState { volume: event.0, ..state })
+            }))
+    }
+}
+
+fn set_volume(app: &mut Application<PlayerState>, volume: i32) {
+    app.dispatcher().post_event(SetVolume(volume));
+    app.poll_dispatched();
+}
+
+#[test]
+fn test_middleware_chain() {
+    let log = Rc::new(RefCell::new(Vec::new()));
+    let logged = log.clone();
+
+    let mut app = Application::new("middleware", PlayerState { volume: 5, locked: false })
+        .middleware(Box::new(|change: &StateChange<PlayerState>, state: PlayerState| {
+            if change.previous.locked && state.locked {
+                Err(InvocationError)
+            } else {
+                Ok(state)
+            }
+        }))
+        .middleware(Box::new(|_change: &StateChange<PlayerState>, state: PlayerState| {
+            Ok(PlayerState { volume: state.volume.max(0).min(10), ..state })
+        }))
+        .middleware(Box::new(move |change: &StateChange<PlayerState>, state: PlayerState| {
+            // Updates posted through the dispatcher have no cause
+            if let Some(cause) = change.cause {
+                let component = cause.component.as_ref().unwrap();
+                logged.borrow_mut().push(format!("{} {} {:?}: {} -> {}", cause.event_type.rsplit("::").next().unwrap(), cause.event, component.name, change.previous.volume, state.volume));
+            }
+            Ok(state)
+        }));
+
+    set_volume(&mut app, 7);
+    set_volume(&mut app, 42);
+    assert_eq!(app.state().volume, 10);
+
+    app.dispatcher().post_update(|state: PlayerState| PlayerState { locked: true, ..state });
+    app.poll_dispatched();
+    set_volume(&mut app, 1);
+    assert_eq!(app.state().volume, 10);
+
+    assert_eq!(*log.borrow(), vec![
+        "SetVolume SetVolume(7) Some(\"player\"): 5 -> 7".to_string(),
+        "SetVolume SetVolume(42) Some(\"player\"): 7 -> 10".to_string(),
+    ]);
+}
+
+#[test]
+fn test_restored_states_skip_middlewares() {
+    let calls = Rc::new(RefCell::new(0));
+    let counted = calls.clone();
+
+    let mut app = Application::new("middleware", PlayerState { volume: 5, locked: false })
+        .undo_history(10)
+        .record_history(true)
+        .middleware(Box::new(move |_change: &StateChange<PlayerState>, state: PlayerState| {
+            *counted.borrow_mut() += 1;
+            Ok(state)
+        }));
+
+    set_volume(&mut app, 7);
+    set_volume(&mut app, 8);
+    assert_eq!(*calls.borrow(), 2);
+
+    assert!(app.undo());
+    assert_eq!(app.state().volume, 7);
+    assert!(app.redo());
+    assert_eq!(app.state().volume, 8);
+    assert!(app.jump_to(0));
+    assert_eq!(app.state().volume, 5);
+    assert_eq!(*calls.borrow(), 2);
+}
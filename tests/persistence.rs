@@ -0,0 +1,180 @@
+extern crate weld;
+extern crate webrender;
+
+use weld::application::Application;
+use weld::middleware::StateChange;
+use weld::model::*;
+use weld::persistence::{Format, Persistence, PersistError, WindowGeometry};
+use webrender::api::LayoutSize;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::str;
+
+#[path = "common/utils.rs"]
+#[macro_use]
+#[allow(dead_code)]
+mod utils;
+
+#[derive(Debug)]
+struct Container {}
+
+impl_dummy_renderer!(Container);
+
+#[derive(Clone, Debug, PartialEq)]
+struct CounterState {
+    count: i32,
+}
+
+impl State for CounterState {
+    fn build(&self) -> Component {
+        Component::new(Container {})
+    }
+
+    // Counting below zero quits
+    fn exit_status(&self) -> Option<i32> {
+        if self.count < 0 {
+            Some(1)
+        } else {
+            None
+        }
+    }
+}
+
+struct CounterFormat;
+
+impl Format<CounterState> for CounterFormat {
+    fn serialize(&self, state: &CounterState) -> Result<Vec<u8>, PersistError> {
+        Ok(format!("count={}", state.count).into_bytes())
+    }
+
+    fn deserialize(&self, data: &[u8]) -> Result<CounterState, PersistError> {
+        let text = str::from_utf8(data).map_err(|e| PersistError::Format(e.to_string()))?;
+        let count = text.trim_start_matches("count=").parse().map_err(|_| PersistError::Format(text.to_string()))?;
+        Ok(CounterState { count })
+    }
+}
+
+fn temp_path(name: &str) -> PathBuf {
+    let path = env::temp_dir().join(format!("weld-{}-{}", name, std::process::id()));
+    let _ = fs::remove_file(&path);
+    path
+}
+
+fn increment(app: &mut Application<CounterState>) {
+    app.dispatcher().post_update(|state: CounterState| CounterState { count: state.count + 1 });
+    app.poll_dispatched();
+}
+
+#[test]
+fn test_restore_state() {
+    let path = temp_path("restore");
+
+    let mut app = Application::new("persistence", CounterState { count: 0 })
+        .persistence(Persistence::new(path.clone(), Box::new(CounterFormat)).save_on_change(true));
+    app.resize(LayoutSize::new(640.0, 480.0));
+    increment(&mut app);
+    increment(&mut app);
+
+    let app = Application::new("persistence", CounterState { count: 0 })
+        .persistence(Persistence::new(path.clone(), Box::new(CounterFormat)));
+    assert_eq!(app.state(), &CounterState { count: 2 });
+
+    let saved = Persistence::new(path.clone(), Box::new(CounterFormat)).load().unwrap().unwrap();
+    assert_eq!(saved.geometry, Some(WindowGeometry { size: (640, 480), position: None }));
+
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_migration() {
+    let path = temp_path("migration");
+
+    // Version 1 stored the count in tens
+    Persistence::new(path.clone(), Box::new(CounterFormat)).save(&CounterState { count: 3 }, None).unwrap();
+
+    let persistence = Persistence::new(path.clone(), Box::new(CounterFormat))
+        .version(2)
+        .migration(1, Box::new(|data| {
+            let old = CounterFormat.deserialize(&data)?;
+            CounterFormat.serialize(&CounterState { count: old.count * 10 })
+        }));
+    let saved = persistence.load().unwrap().unwrap();
+    assert_eq!(saved.state, CounterState { count: 30 });
+    assert_eq!(saved.geometry, None);
+
+    // A state saved by a newer version can't be read
+    persistence.save(&saved.state, None).unwrap();
+    match Persistence::new(path.clone(), Box::new(CounterFormat)).load() {
+        Err(PersistError::Version(2)) => {},
+        _ => panic!("expected a version error")
+    }
+
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_restored_state_skips_middlewares() {
+    let path = temp_path("middleware");
+    Persistence::new(path.clone(), Box::new(CounterFormat)).save(&CounterState { count: 3 }, None).unwrap();
+
+    let app = Application::new("persistence", CounterState { count: 0 })
+        .middleware(Box::new(|_change: &StateChange<CounterState>, _state: CounterState| Err(InvocationError)))
+        .persistence(Persistence::new(path.clone(), Box::new(CounterFormat)));
+    assert_eq!(app.state(), &CounterState { count: 3 });
+
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_restore_quitting_state() {
+    let path = temp_path("quitting");
+
+    let mut app = Application::new("persistence", CounterState { count: 0 })
+        .persistence(Persistence::new(path.clone(), Box::new(CounterFormat)).save_on_change(true));
+    app.dispatcher().post_update(|_state: CounterState| CounterState { count: -1 });
+    app.poll_dispatched();
+    assert_eq!(app.exit_status(), Some(1));
+
+    let app = Application::new("persistence", CounterState { count: 0 })
+        .persistence(Persistence::new(path.clone(), Box::new(CounterFormat)));
+    assert_eq!(app.state(), &CounterState { count: -1 });
+    assert_eq!(app.exit_status(), None);
+
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_undo_saves_on_change() {
+    let path = temp_path("undo");
+
+    let mut app = Application::new("persistence", CounterState { count: 0 })
+        .undo_history(10)
+        .persistence(Persistence::new(path.clone(), Box::new(CounterFormat)).save_on_change(true));
+    increment(&mut app);
+    increment(&mut app);
+    assert!(app.undo());
+
+    let saved = Persistence::new(path.clone(), Box::new(CounterFormat)).load().unwrap().unwrap();
+    assert_eq!(saved.state, CounterState { count: 1 });
+
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_history_starts_at_restored_state() {
+    let path = temp_path("history");
+    Persistence::new(path.clone(), Box::new(CounterFormat)).save(&CounterState { count: 5 }, None).unwrap();
+
+    let mut app = Application::new("persistence", CounterState { count: 0 })
+        .record_history(true)
+        .persistence(Persistence::new(path.clone(), Box::new(CounterFormat)));
+    let states: Vec<i32> = app.history().unwrap().entries().iter().map(|entry| entry.state.count).collect();
+    assert_eq!(states, vec![5]);
+
+    increment(&mut app);
+    assert!(app.jump_to(0));
+    assert_eq!(app.state(), &CounterState { count: 5 });
+
+    fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,135 @@
+extern crate weld;
+extern crate webrender;
+
+use weld::application::Application;
+use weld::gesture::Gesture;
+use weld::input::{Interaction, Modifiers, MouseButton, PointerEvent};
+use weld::model::*;
+use weld::recording::{RecordedInput, Recorder, Recording};
+use weld::window::{ChildWindow, ChildWindowClosed, WindowEvent};
+use weld::layout::Percent;
+use weld::layout::FlexStyle::*;
+use webrender::api::{LayoutPoint, LayoutSize, WorldPoint};
+use std::env;
+use std::fs;
+use std::time::Instant;
+
+#[path = "common/utils.rs"]
+#[macro_use]
+#[allow(dead_code)]
+mod utils;
+
+#[derive(Debug)]
+struct Container {}
+
+impl_dummy_renderer!(Container);
+
+#[derive(Clone, Debug, PartialEq)]
+struct ClickState {
+    clicks: u32,
+    double_clicks: u32,
+}
+
+impl State for ClickState {
+    fn build(&self) -> Component {
+        Component::new(Container {})
+            .styles(vec![Width(100.percent()), Height(100.percent())])
+            .on(Box::new(|state: ClickState, event: &Gesture| {
+                match *event {
+                    Gesture::Click(_) => Ok(ClickState { clicks: state.clicks + 1, ..state }),
+                    Gesture::DoubleClick(_) => Ok(ClickState { double_clicks: state.double_clicks + 1, ..state }),
+                    _ => Err(InvocationError)
+                }
+            }))
+    }
+}
+
+fn pointer(x: f32, y: f32) -> PointerEvent {
+    PointerEvent {
+        button: Some(MouseButton::Left),
+        click_count: 1,
+        modifiers: Modifiers { shift: true, ..Modifiers::none() },
+        window_position: WorldPoint::new(x, y),
+        local_position: LayoutPoint::new(x, y),
+        timestamp: Instant::now(),
+    }
+}
+
+fn click(app: &mut Application<ClickState>, x: f32, y: f32) {
+    app.inject(WindowEvent::Interaction(WorldPoint::new(x, y), Interaction::Pressed(pointer(x, y))));
+    app.inject(WindowEvent::Interaction(WorldPoint::new(x, y), Interaction::Released(pointer(x, y))));
+}
+
+#[test]
+fn test_record_and_replay() {
+    let path = env::temp_dir().join(format!("weld-recording-{}", std::process::id()));
+    let initial = ClickState { clicks: 0, double_clicks: 0 };
+
+    let mut app = Application::new("recording", initial.clone()).record(Recorder::create(&path).unwrap());
+    app.inject(WindowEvent::Resized(LayoutSize::new(100.0, 100.0)));
+    click(&mut app, 10.5, 20.25);
+    click(&mut app, 10.5, 20.25);
+    assert_eq!(app.state(), &ClickState { clicks: 2, double_clicks: 1 });
+
+    let recording = Recording::load(&path).unwrap();
+    assert_eq!(recording.events.len(), 5);
+    match recording.events[1].input {
+        RecordedInput::Interaction(Interaction::Pressed(ref e)) => {
+            assert_eq!(e.window_position, WorldPoint::new(10.5, 20.25));
+            assert!(e.modifiers.shift);
+        },
+        ref input => panic!("unexpected input {:?}", input)
+    }
+
+    let mut replayed = Application::new("recording", initial);
+    recording.replay(&mut replayed);
+    assert_eq!(replayed.state(), app.state());
+
+    // Both sessions seed their component ids the same way
+    let root_id = |app: &Application<ClickState>| app.tree().inspect().id().to_string();
+    assert_eq!(root_id(&app), "s0");
+    assert_eq!(root_id(&replayed), root_id(&app));
+
+    fs::remove_file(&path).unwrap();
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct PaletteState {
+    closes: u32,
+}
+
+impl State for PaletteState {
+    fn build(&self) -> Component {
+        Component::new(Container {})
+            .on(Box::new(|state: PaletteState, event: &ChildWindowClosed| {
+                assert_eq!(event.key, "palette");
+                Ok(PaletteState { closes: state.closes + 1 })
+            }))
+    }
+
+    fn windows(&self) -> Vec<ChildWindow> {
+        vec![ChildWindow::new("palette", Component::new(Container {}))]
+    }
+}
+
+#[test]
+fn test_replay_child_window_close() {
+    let path = env::temp_dir().join(format!("weld-recording-child-{}", std::process::id()));
+
+    let mut app = Application::new("recording", PaletteState { closes: 0 }).record(Recorder::create(&path).unwrap());
+    assert_eq!(app.child_windows(), vec!["palette"]);
+    app.inject_into("palette", WindowEvent::WindowClosed);
+    assert!(app.child_windows().is_empty());
+    assert_eq!(app.state().closes, 1);
+
+    let recording = Recording::load(&path).unwrap();
+    assert_eq!(recording.events.len(), 1);
+    assert_eq!(recording.events[0].window, Some("palette".to_string()));
+
+    let mut replayed = Application::new("recording", PaletteState { closes: 0 });
+    recording.replay(&mut replayed);
+    assert!(replayed.child_windows().is_empty());
+    assert_eq!(replayed.state(), app.state());
+
+    fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,165 @@
+extern crate weld;
+extern crate webrender;
+
+use weld::application::Application;
+use weld::gesture::Gesture;
+use weld::model::*;
+use weld::remote::{Json, RemoteClient, RemoteServer, MAX_REQUEST_LENGTH};
+use weld::window::Screenshot;
+use weld::layout::{Percent, Point};
+use weld::layout::FlexStyle::*;
+use webrender::api::LayoutSize;
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpStream;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+
+#[path = "common/utils.rs"]
+#[macro_use]
+#[allow(dead_code)]
+mod utils;
+
+#[derive(Debug)]
+struct Container {}
+
+impl_dummy_renderer!(Container);
+
+#[derive(Clone, Debug)]
+struct CounterState {
+    clicks: u32,
+}
+
+impl State for CounterState {
+    fn build(&self) -> Component {
+        Component::new(Container {})
+            .name("counter")
+            .styles(vec![Width(100.percent()), Height(100.percent()), Padding(20.point())])
+            .on(Box::new(|state: CounterState, event: &Gesture| {
+                match *event {
+                    Gesture::Click(_) => Ok(CounterState { clicks: state.clicks + 1 }),
+                    _ => Err(InvocationError)
+                }
+            }))
+            .child(
+                Component::new(Container {})
+                    .name("panel")
+                    .styles(vec![Padding(5.point())])
+                    .child(Component::new(Container {}).name("label").styles(vec![Width(20.point()), Height(10.point())]))
+            )
+    }
+}
+
+/// Tells the application loop to stop once the client is done, even when an assertion failed.
+struct SetOnDrop(Arc<AtomicBool>);
+
+impl Drop for SetOnDrop {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+}
+
+fn params(entries: Vec<(&str, Json)>) -> Json {
+    Json::Object(entries.into_iter().map(|(key, value)| (key.to_string(), value)).collect())
+}
+
+#[test]
+fn test_remote_inspection() {
+    let server = RemoteServer::bind_tcp("127.0.0.1:0").unwrap();
+    let address = server.local_addr().unwrap();
+    let mut app = Application::new("remote", CounterState { clicks: 0 }).remote(server);
+    app.resize(LayoutSize::new(100.0, 50.0));
+
+    let done = Arc::new(AtomicBool::new(false));
+    let client_done = SetOnDrop(done.clone());
+    let client = thread::spawn(move || {
+        let _client_done = client_done;
+        let mut client = RemoteClient::connect_tcp(address).unwrap();
+
+        let tree = client.request("tree", params(vec![("layout", Json::Bool(true))])).unwrap();
+        assert_eq!(tree.get("name"), Some(&Json::String("counter".to_string())));
+        assert_eq!(tree.get("events"), Some(&Json::Array(vec![Json::String("weld::gesture::Gesture".to_string())])));
+        assert_eq!(tree.get("bounds").and_then(|bounds| bounds.get("width")), Some(&Json::Number(100.0)));
+
+        // Bounds are relative to the window, so they can be used for injected inputs
+        let label = &tree.get("children").and_then(Json::as_array).unwrap()[0].get("children").and_then(Json::as_array).unwrap()[0];
+        assert_eq!(label.get("name"), Some(&Json::String("label".to_string())));
+        let bounds = label.get("bounds").unwrap();
+        assert_eq!((bounds.get("x"), bounds.get("y")), (Some(&Json::Number(25.0)), Some(&Json::Number(25.0))));
+
+        for input in &["press 10 10 - left 1", "release 10 10 - left 1"] {
+            client.request("inject", params(vec![("input", Json::String(input.to_string()))])).unwrap();
+        }
+        let state = client.request("state", Json::Null).unwrap();
+        assert!(state.as_str().unwrap().contains("clicks: 1"));
+
+        assert!(client.request("inject", params(vec![("input", Json::String("jump".to_string()))])).is_err());
+        assert!(client.request("screenshot", Json::Null).is_err());
+    });
+
+    while !done.load(Ordering::SeqCst) {
+        app.poll_dispatched();
+        thread::sleep(Duration::from_millis(1));
+    }
+    client.join().unwrap();
+    assert_eq!(app.state().clicks, 1);
+}
+
+#[test]
+fn test_request_too_long() {
+    let server = RemoteServer::bind_tcp("127.0.0.1:0").unwrap();
+    let address = server.local_addr().unwrap();
+    let _app = Application::new("remote", CounterState { clicks: 0 }).remote(server);
+
+    let mut stream = TcpStream::connect(address).unwrap();
+    stream.write_all(&vec![b' '; MAX_REQUEST_LENGTH + 1]).unwrap();
+
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    reader.read_line(&mut line).unwrap();
+    assert_eq!(Json::parse(&line).unwrap().get("error"), Some(&Json::String("request too long".to_string())));
+    // The client is disconnected
+    line.clear();
+    assert_eq!(reader.read_line(&mut line).unwrap(), 0);
+}
+
+#[test]
+fn test_screenshot_result() {
+    let screenshot = Screenshot { width: 2, height: 1, pixels: vec![255, 0, 0, 255, 0, 0, 255, 128] };
+    let result = Json::from(&screenshot);
+    assert_eq!(result.get("width"), Some(&Json::Number(2.0)));
+    assert_eq!(result.get("height"), Some(&Json::Number(1.0)));
+    assert_eq!(result.get("format").and_then(Json::as_str), Some("rgba8"));
+    assert_eq!(result.get("data").and_then(Json::as_str), Some("/wAA/wAA/4A="));
+}
+
+#[test]
+fn test_json() {
+    let json = Json::parse(r#" {"a": [1, -2.5e1, true, null], "b": "x\"é\n"} "#).unwrap();
+    assert_eq!(json.get("a").and_then(Json::as_array).map(|values| values.len()), Some(4));
+    assert_eq!(json.get("b").and_then(Json::as_str), Some("x\"é\n"));
+    assert_eq!(Json::parse(&json.to_string()), Ok(json));
+    assert!(Json::parse("{\"a\": }").is_err());
+}
+
+#[test]
+fn test_json_errors() {
+    // Nesting is limited instead of overflowing the stack
+    assert!(Json::parse(&format!("{}{}", "[".repeat(10), "]".repeat(10))).is_ok());
+    assert!(Json::parse(&format!("{}{}", "[".repeat(100_000), "]".repeat(100_000))).is_err());
+    assert!(Json::parse(&"{\"a\":".repeat(100_000)).is_err());
+
+    assert_eq!(Json::parse(r#""😀""#), Ok(Json::String("😀".to_string())));
+    // A high surrogate must be followed by a low one
+    assert!(Json::parse(r#""\ud83dA""#).is_err());
+    assert!(Json::parse(r#""\ud83d\ud83d""#).is_err());
+    assert!(Json::parse(r#""\ud83dx""#).is_err());
+    assert!(Json::parse(r#""\ude00""#).is_err());
+
+    assert!(Json::parse(r#""\x""#).is_err());
+    assert!(Json::parse(r#""\u+041""#).is_err());
+    assert!(Json::parse(r#""\u00g1""#).is_err());
+    assert!(Json::parse(r#""\u00"#).is_err());
+    assert!(Json::parse(r#""\"#).is_err());
+}
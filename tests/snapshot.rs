@@ -0,0 +1,85 @@
+extern crate weld;
+extern crate webrender;
+
+use weld::application::Application;
+use weld::gesture::Gesture;
+use weld::model::*;
+use weld::snapshot::{assert_snapshot, diff, snapshot, snapshot_with_layout};
+use weld::layout::{Percent, Point};
+use weld::layout::FlexStyle::*;
+use webrender::api::LayoutSize;
+
+#[path = "common/utils.rs"]
+#[macro_use]
+#[allow(dead_code)]
+mod utils;
+
+#[derive(Debug)]
+struct Container {}
+
+#[derive(Debug)]
+struct Item {}
+
+impl_dummy_renderer!(Container);
+impl_dummy_renderer!(Item);
+
+#[derive(Clone, Debug)]
+struct ListState {
+    items: usize,
+}
+
+impl State for ListState {
+    fn build(&self) -> Component {
+        let items: Vec<Component> = (0..self.items).map(|index| {
+            Component::new(Item {}).name(format!("item-{}", index))
+        }).collect();
+
+        Component::new(Container {})
+            .name("list")
+            .on(Box::new(|state: ListState, _event: &Gesture| Ok(ListState { items: state.items + 1 })))
+            .children(items)
+    }
+}
+
+#[test]
+fn test_snapshot() {
+    let tree = ListState { items: 2 }.build();
+
+    assert_snapshot(&snapshot(&tree), "\
+[] Container \"list\"
+    events: [weld::gesture::Gesture]
+    [0] Item \"item-0\"
+    [1] Item \"item-1\"
+");
+}
+
+#[test]
+fn test_snapshot_with_layout() {
+    #[derive(Clone, Debug)]
+    struct SizedState {}
+
+    impl State for SizedState {
+        fn build(&self) -> Component {
+            Component::new(Container {})
+                .styles(vec![Width(100.percent()), Height(100.percent())])
+                .child(Component::new(Item {}).styles(vec![Width(20.point()), Height(10.point())]))
+        }
+    }
+
+    let mut app = Application::new("snapshot", SizedState {});
+    app.resize(LayoutSize::new(200.0, 100.0));
+
+    let text = {
+        let tree = app.tree();
+        let layout_context = app.layout_context().borrow();
+        snapshot_with_layout(&tree, &layout_context)
+    };
+    let bounds: Vec<&str> = text.lines().filter(|line| line.trim_start().starts_with("bounds:")).collect();
+    assert_eq!(bounds, vec!["    bounds: (0, 0) 200x100", "        bounds: (0, 0) 20x10"]);
+}
+
+#[test]
+fn test_diff() {
+    assert_eq!(diff("a\nb\n", "a\nb\n"), None);
+    assert_eq!(diff("a\nc\nd\n", "a\nb\nc\n").unwrap(), "  a\n- b\n  c\n+ d\n");
+}
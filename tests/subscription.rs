@@ -0,0 +1,99 @@
+extern crate weld;
+extern crate webrender;
+extern crate futures;
+
+use weld::application::Application;
+use weld::model::*;
+use weld::subscription::{Subscription, Tick, MAX_ITEMS_PER_POLL};
+use futures::stream;
+use std::time::{Duration, Instant};
+
+#[path = "common/utils.rs"]
+#[macro_use]
+#[allow(dead_code)]
+mod utils;
+
+#[derive(Debug)]
+struct Container {}
+
+impl_dummy_renderer!(Container);
+
+#[derive(Clone, Debug)]
+struct ClockState {
+    ticks: u32,
+    answer: Option<u32>,
+}
+
+impl State for ClockState {
+    fn build(&self) -> Component {
+        Component::new(Container {})
+    }
+
+    fn subscriptions(&self) -> Vec<Subscription<ClockState>> {
+        let mut subscriptions = vec![
+            Subscription::stream("answer", || stream::once::<u32, ()>(Ok(42)), Box::new(|state: ClockState, answer: &u32| {
+                Ok(ClockState { answer: Some(*answer), ..state })
+            }))
+        ];
+
+        // Stops itself after two ticks
+        if self.ticks < 2 {
+            subscriptions.push(Subscription::interval("clock", Duration::from_millis(100), Box::new(|state: ClockState, _tick: &Tick| {
+                Ok(ClockState { ticks: state.ticks + 1, ..state })
+            })));
+        }
+
+        subscriptions
+    }
+}
+
+#[test]
+fn test_subscriptions() {
+    let start = Instant::now();
+    let mut app = Application::new("subscriptions", ClockState { ticks: 0, answer: None });
+
+    app.poll_subscriptions(start);
+    assert_eq!(app.state().ticks, 0);
+    assert_eq!(app.state().answer, Some(42));
+
+    // Missed periods are skipped, and the next tick stays on the schedule
+    app.poll_subscriptions(start + Duration::from_millis(350));
+    assert_eq!(app.state().ticks, 1);
+    app.poll_subscriptions(start + Duration::from_millis(399));
+    assert_eq!(app.state().ticks, 1);
+
+    app.poll_subscriptions(start + Duration::from_millis(400));
+    assert_eq!(app.state().ticks, 2);
+    app.poll_subscriptions(start + Duration::from_secs(10));
+    assert_eq!(app.state().ticks, 2);
+}
+
+#[derive(Clone, Debug)]
+struct FloodState {
+    items: usize,
+}
+
+impl State for FloodState {
+    fn build(&self) -> Component {
+        Component::new(Container {})
+    }
+
+    fn subscriptions(&self) -> Vec<Subscription<FloodState>> {
+        vec![
+            Subscription::stream("flood", || stream::repeat::<u32, ()>(1), Box::new(|state: FloodState, _item: &u32| {
+                Ok(FloodState { items: state.items + 1 })
+            }))
+        ]
+    }
+}
+
+#[test]
+fn test_stream_that_is_always_ready() {
+    let mut app = Application::new("subscriptions", FloodState { items: 0 });
+
+    // Every poll returns after a bounded number of items instead of spinning forever
+    app.poll_subscriptions(Instant::now());
+    assert_eq!(app.state().items, MAX_ITEMS_PER_POLL);
+    app.poll_subscriptions(Instant::now());
+    assert_eq!(app.state().items, 2 * MAX_ITEMS_PER_POLL);
+}
@@ -0,0 +1,101 @@
+extern crate weld;
+extern crate webrender;
+
+use weld::application::Application;
+use weld::gesture::Gesture;
+use weld::input::{TouchEvent, TouchPhase};
+use weld::model::*;
+use weld::window::WindowEvent;
+use weld::layout::{FlexDirection, Point};
+use weld::layout::FlexStyle::*;
+use webrender::api::{LayoutPoint, LayoutSize, WorldPoint};
+use std::time::Instant;
+
+#[path = "common/utils.rs"]
+#[macro_use]
+#[allow(dead_code)]
+mod utils;
+
+#[derive(Debug)]
+struct Container {}
+
+impl_dummy_renderer!(Container);
+
+fn container() -> Component {
+    Component::new(Container {})
+}
+
+#[derive(Clone, Debug)]
+struct TouchState {
+    left: Vec<(u64, TouchPhase, LayoutPoint)>,
+    right: Vec<(u64, TouchPhase, LayoutPoint)>,
+    scale: f32,
+}
+
+impl State for TouchState {
+    fn build(&self) -> Component {
+        container()
+            .styles(vec![Width(200.point()), Height(100.point()), FlexDirection(FlexDirection::Row)])
+            .child(
+                container()
+                    .name("left")
+                    .styles(vec![Width(100.point()), Height(100.point())])
+                    .on(Box::new(|mut state: TouchState, event: &TouchEvent| {
+                        state.left.push((event.id, event.phase, event.local_position));
+                        Ok(state)
+                    }))
+                    .on(Box::new(|mut state: TouchState, event: &Gesture| {
+                        match *event {
+                            Gesture::Pinch(ref e) => {
+                                state.scale = e.scale;
+                                Ok(state)
+                            }
+                            _ => Err(InvocationError)
+                        }
+                    }))
+            )
+            .child(
+                container()
+                    .name("right")
+                    .styles(vec![Width(100.point()), Height(100.point())])
+                    .on(Box::new(|mut state: TouchState, event: &TouchEvent| {
+                        state.right.push((event.id, event.phase, event.local_position));
+                        Ok(state)
+                    }))
+            )
+    }
+}
+
+fn touch(id: u64, phase: TouchPhase, x: f32, y: f32) -> WindowEvent {
+    WindowEvent::Touch(TouchEvent {
+        id,
+        phase,
+        window_position: WorldPoint::new(x, y),
+        local_position: LayoutPoint::new(x, y),
+        timestamp: Instant::now(),
+    })
+}
+
+#[test]
+fn test_touch_sequence() {
+    let mut app = Application::new("touch", TouchState { left: Vec::new(), right: Vec::new(), scale: 1.0 });
+    app.resize(LayoutSize::new(200.0, 100.0));
+
+    app.inject(touch(1, TouchPhase::Started, 10.0, 50.0));
+    app.inject(touch(2, TouchPhase::Started, 150.0, 50.0));
+    app.inject(touch(2, TouchPhase::Moved, 290.0, 50.0));
+    app.inject(touch(1, TouchPhase::Cancelled, 10.0, 50.0));
+    app.inject(touch(2, TouchPhase::Ended, 290.0, 50.0));
+
+    let state = app.state();
+    assert_eq!(state.left, vec![
+        (1, TouchPhase::Started, LayoutPoint::new(10.0, 50.0)),
+        (1, TouchPhase::Cancelled, LayoutPoint::new(10.0, 50.0)),
+    ]);
+    assert_eq!(state.right, vec![
+        (2, TouchPhase::Started, LayoutPoint::new(50.0, 50.0)),
+        (2, TouchPhase::Moved, LayoutPoint::new(190.0, 50.0)),
+        (2, TouchPhase::Ended, LayoutPoint::new(190.0, 50.0)),
+    ]);
+    assert_eq!(state.scale, 2.0);
+}
@@ -0,0 +1,109 @@
+extern crate weld;
+extern crate webrender;
+
+use weld::animation::Easing;
+use weld::application::Application;
+use weld::model::*;
+use weld::transition::{Transition, TransitionProperty};
+use weld::window::{WindowEvent, WindowResized};
+use weld::layout::Point;
+use weld::layout::FlexStyle::*;
+use webrender::api::LayoutSize;
+use std::time::Duration;
+
+#[path = "common/utils.rs"]
+#[macro_use]
+#[allow(dead_code)]
+mod utils;
+
+#[derive(Debug)]
+struct Container {}
+
+impl_dummy_renderer!(Container);
+
+#[derive(Clone, Debug)]
+struct GrowState {
+    width: i32,
+}
+
+impl State for GrowState {
+    fn build(&self) -> Component {
+        Component::new(Container {})
+            .on(Box::new(|_state: GrowState, _event: &WindowResized| {
+                Ok(GrowState { width: 200 })
+            }))
+            .child(
+                Component::new(Container {})
+                    .name("box")
+                    .styles(vec![Width(self.width.point()), Height(10.point())])
+                    .transition(Transition::new(TransitionProperty::Width, Duration::from_millis(100)).easing(Easing::Linear))
+            )
+    }
+}
+
+fn box_width(app: &Application<GrowState>) -> f32 {
+    let tree = app.tree();
+    let layout_context = app.layout_context().borrow();
+    layout_context.get_bounds(tree.find_by_name("box").unwrap().inspect()).size.width
+}
+
+#[test]
+fn test_width_transition() {
+    let mut app = Application::new("transition", GrowState { width: 100 });
+    app.resize(LayoutSize::new(400.0, 400.0));
+    assert_eq!(box_width(&app), 100.0);
+    assert!(!app.animating());
+
+    app.inject(WindowEvent::Resized(LayoutSize::new(400.0, 400.0)));
+    assert_eq!(app.state().width, 200);
+    assert_eq!(box_width(&app), 100.0);
+    assert!(app.animating());
+
+    app.advance(Duration::from_millis(50));
+    assert_eq!(box_width(&app), 150.0);
+
+    app.advance(Duration::from_millis(50));
+    assert_eq!(box_width(&app), 200.0);
+    assert!(!app.animating());
+}
+
+#[derive(Clone, Debug)]
+struct ListState {
+    widths: Vec<i32>,
+}
+
+impl State for ListState {
+    fn build(&self) -> Component {
+        let items: Vec<Component> = self.widths.iter().map(|&width| {
+            Component::new(Container {})
+                .name("item")
+                .styles(vec![Width(width.point()), Height(10.point())])
+                .transition(Transition::new(TransitionProperty::Width, Duration::from_millis(100)).easing(Easing::Linear))
+        }).collect();
+
+        Component::new(Container {})
+            .on(Box::new(|_state: ListState, _event: &WindowResized| {
+                Ok(ListState { widths: vec![100, 200] })
+            }))
+            .children(items)
+    }
+}
+
+fn item_widths(app: &Application<ListState>) -> Vec<f32> {
+    let tree = app.tree();
+    let layout_context = app.layout_context().borrow();
+    tree.inspect().children().iter().map(|item| layout_context.get_bounds(item.inspect()).size.width).collect()
+}
+
+#[test]
+fn test_same_named_siblings_transition_apart() {
+    let mut app = Application::new("transition", ListState { widths: vec![100, 100] });
+    app.resize(LayoutSize::new(400.0, 400.0));
+
+    app.inject(WindowEvent::Resized(LayoutSize::new(400.0, 400.0)));
+    app.advance(Duration::from_millis(50));
+    assert_eq!(item_widths(&app), vec![100.0, 150.0]);
+
+    app.advance(Duration::from_millis(50));
+    assert_eq!(item_widths(&app), vec![100.0, 200.0]);
+}
@@ -0,0 +1,160 @@
+extern crate weld;
+extern crate webrender;
+extern crate glutin;
+
+use weld::application::Application;
+use weld::input::{Interaction, Modifiers, MouseButton, PointerEvent};
+use weld::model::*;
+use weld::undo::UndoStep;
+use weld::window::WindowEvent;
+use webrender::api::{LayoutPoint, LayoutSize, WorldPoint};
+use std::time::Instant;
+
+#[path = "common/utils.rs"]
+#[macro_use]
+#[allow(dead_code)]
+mod utils;
+
+#[derive(Debug)]
+struct Container {}
+
+impl_dummy_renderer!(Container);
+
+#[derive(Clone, Debug, PartialEq)]
+struct EditorState {
+    text: String,
+    cursor: usize,
+}
+
+impl State for EditorState {
+    fn build(&self) -> Component {
+        Component::new(Container {})
+    }
+
+    fn undo_step(&self, previous: &Self) -> UndoStep {
+        if self.text == previous.text {
+            UndoStep::Ignore
+        } else {
+            UndoStep::Record
+        }
+    }
+}
+
+fn type_text(app: &mut Application<EditorState>, text: &'static str) {
+    app.dispatcher().post_update(move |state: EditorState| {
+        let text = state.text + text;
+        EditorState { cursor: text.len(), text }
+    });
+    app.poll_dispatched();
+}
+
+fn move_cursor(app: &mut Application<EditorState>, cursor: usize) {
+    app.dispatcher().post_update(move |state: EditorState| EditorState { cursor, ..state });
+    app.poll_dispatched();
+}
+
+#[test]
+fn test_undo_redo() {
+    let mut app = Application::new("undo", EditorState { text: String::new(), cursor: 0 }).undo_history(2);
+    assert!(!app.can_undo());
+
+    type_text(&mut app, "a");
+    type_text(&mut app, "b");
+    move_cursor(&mut app, 0);
+    type_text(&mut app, "c");
+
+    // The oldest step is forgotten beyond the limit
+    assert!(app.undo());
+    assert_eq!(app.state().text, "ab");
+    assert!(app.undo());
+    assert_eq!(app.state().text, "a");
+    assert!(!app.undo());
+
+    assert!(app.redo());
+    assert_eq!(app.state().text, "ab");
+    assert!(app.can_redo());
+
+    // A new transition drops what could be redone
+    type_text(&mut app, "d");
+    assert!(!app.can_redo());
+    assert!(app.undo());
+    assert_eq!(app.state().text, "ab");
+}
+
+#[test]
+fn test_transaction() {
+    let mut app = Application::new("undo", EditorState { text: String::new(), cursor: 0 }).undo_history(10);
+    type_text(&mut app, "a");
+
+    app.begin_transaction();
+    type_text(&mut app, "b");
+    type_text(&mut app, "c");
+    app.end_transaction();
+
+    assert!(app.undo());
+    assert_eq!(app.state().text, "a");
+    assert!(app.undo());
+    assert_eq!(app.state().text, "");
+}
+
+fn pointer(app: &mut Application<EditorState>, pressed: bool, button: MouseButton) {
+    let event = PointerEvent {
+        button: Some(button),
+        click_count: 1,
+        modifiers: Modifiers::none(),
+        window_position: WorldPoint::new(5.0, 5.0),
+        local_position: LayoutPoint::new(5.0, 5.0),
+        timestamp: Instant::now(),
+    };
+    let interaction = if pressed { Interaction::Pressed(event) } else { Interaction::Released(event) };
+    app.inject(WindowEvent::Interaction(WorldPoint::new(5.0, 5.0), interaction));
+}
+
+#[test]
+fn test_transaction_with_two_buttons() {
+    let mut app = Application::new("undo", EditorState { text: String::new(), cursor: 0 }).undo_history(10);
+    app.resize(LayoutSize::new(10.0, 10.0));
+
+    pointer(&mut app, true, MouseButton::Left);
+    type_text(&mut app, "a");
+    pointer(&mut app, true, MouseButton::Right);
+    type_text(&mut app, "b");
+    pointer(&mut app, false, MouseButton::Right);
+    type_text(&mut app, "c");
+    pointer(&mut app, false, MouseButton::Left);
+    type_text(&mut app, "d");
+
+    assert!(app.undo());
+    assert_eq!(app.state().text, "abc");
+    assert!(app.undo());
+    assert_eq!(app.state().text, "");
+}
+
+#[test]
+fn test_transaction_with_missing_release() {
+    let mut app = Application::new("undo", EditorState { text: String::new(), cursor: 0 }).undo_history(10);
+    app.resize(LayoutSize::new(10.0, 10.0));
+
+    // The release happened outside of the window and was never reported
+    pointer(&mut app, true, MouseButton::Left);
+    type_text(&mut app, "a");
+    pointer(&mut app, true, MouseButton::Left);
+    type_text(&mut app, "b");
+    pointer(&mut app, false, MouseButton::Left);
+    type_text(&mut app, "c");
+
+    // Losing the focus ends the transaction as well
+    pointer(&mut app, true, MouseButton::Left);
+    type_text(&mut app, "d");
+    app.inject(WindowEvent::GlutinWindowEvent(glutin::WindowEvent::Focused(false)));
+    type_text(&mut app, "e");
+
+    assert!(app.undo());
+    assert_eq!(app.state().text, "abcd");
+    assert!(app.undo());
+    assert_eq!(app.state().text, "abc");
+    assert!(app.undo());
+    assert_eq!(app.state().text, "ab");
+    assert!(app.undo());
+    assert_eq!(app.state().text, "");
+}
@@ -0,0 +1,158 @@
+extern crate weld;
+extern crate webrender;
+extern crate futures;
+extern crate tokio_core;
+
+use weld::layout_context::{to_device_rect, LayoutContext};
+use weld::model::*;
+use weld::gesture::GestureConfig;
+use weld::window::{clamp_size, device_size, logical_point, logical_size, window_property_changes, EventStream, Timer, WindowEvent, WindowProperties, WindowPropertyChanges};
+use weld::layout::Percent;
+use weld::layout::FlexStyle::*;
+use webrender::api::{LayoutPoint, LayoutRect, LayoutSize, WorldPoint};
+use futures::{Future, Stream};
+use futures::future::Either;
+use tokio_core::reactor::{Core, Timeout};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[path = "common/utils.rs"]
+#[macro_use]
+#[allow(dead_code)]
+mod utils;
+
+#[derive(Debug)]
+struct Container {}
+
+impl_dummy_renderer!(Container);
+
+#[test]
+fn test_dpi_conversions() {
+    assert_eq!(logical_size((1600, 1200), 2.0), LayoutSize::new(800.0, 600.0));
+    assert_eq!(logical_point((300.0, 150.0), 1.5), WorldPoint::new(200.0, 100.0));
+    assert_eq!(device_size(&LayoutSize::new(800.0, 600.0), 1.5), (1200, 900));
+    // Partial device pixels are rounded instead of truncated
+    assert_eq!(device_size(&LayoutSize::new(101.0, 33.0), 1.5), (152, 50));
+
+    let rect = LayoutRect::new(LayoutPoint::new(10.0, 20.0), LayoutSize::new(30.0, 40.0));
+    assert_eq!(to_device_rect(&rect, 2.0), LayoutRect::new(LayoutPoint::new(20.0, 40.0), LayoutSize::new(60.0, 80.0)));
+}
+
+#[test]
+fn test_device_pixel_ratio_change() {
+    let tree = Component::new(Container {})
+        .styles(vec![Width(100.percent()), Height(50.percent())]);
+    let mut layout_context = LayoutContext::new();
+    let window_size = (1600, 1200);
+
+    // Moving the window to a monitor with twice the DPI keeps its device size, but halves the
+    // logical size the layout works with
+    for &(ratio, expected) in &[(1.0, LayoutSize::new(1600.0, 600.0)), (2.0, LayoutSize::new(800.0, 300.0))] {
+        layout_context.update_layout(&tree, &logical_size(window_size, ratio));
+        let bounds = layout_context.absolute_bounds(tree.inspect());
+        assert_eq!(bounds.size, expected);
+        assert_eq!(to_device_rect(&bounds, ratio).size, LayoutSize::new(1600.0, 600.0));
+    }
+}
+
+#[test]
+fn test_size_limits() {
+    let min_size = Some(LayoutSize::new(320.0, 240.0));
+    let max_size = Some(LayoutSize::new(800.0, 600.0));
+    assert_eq!(clamp_size(&LayoutSize::new(100.0, 700.0), min_size, max_size), LayoutSize::new(320.0, 600.0));
+    assert_eq!(clamp_size(&LayoutSize::new(400.0, 300.0), min_size, max_size), LayoutSize::new(400.0, 300.0));
+    assert_eq!(clamp_size(&LayoutSize::new(1000.0, 100.0), None, max_size), LayoutSize::new(800.0, 100.0));
+
+    // The limits are logical, so on a monitor with twice the DPI they allow twice the device pixels
+    let limited = clamp_size(&logical_size((2000, 1000), 2.0), min_size, max_size);
+    assert_eq!(device_size(&limited, 2.0), (1600, 1000));
+}
+
+#[test]
+fn test_window_property_changes() {
+    let wanted = WindowProperties::new().title("Editor").size(800, 600);
+
+    // Nothing is known about a new window
+    assert_eq!(window_property_changes(&wanted, &WindowPropertyChanges::default()), WindowPropertyChanges {
+        title: Some("Editor".to_string()),
+        size: Some((800, 600)),
+        fullscreen: Some(false),
+    });
+
+    let mut current = WindowPropertyChanges::default();
+    current.merge(&window_property_changes(&wanted, &current));
+    assert_eq!(window_property_changes(&wanted, &current), WindowPropertyChanges::default());
+
+    // The user resized the window and the state asks for the same size again
+    current.size = Some((1000, 700));
+    assert_eq!(window_property_changes(&wanted, &current), WindowPropertyChanges { size: Some((800, 600)), ..WindowPropertyChanges::default() });
+
+    // Properties that aren't set are left alone
+    let untitled = WindowProperties::new().fullscreen(true);
+    assert_eq!(window_property_changes(&untitled, &current), WindowPropertyChanges { fullscreen: Some(true), ..WindowPropertyChanges::default() });
+}
+
+#[test]
+fn test_timer() {
+    let (fired_tx, fired_rx) = mpsc::channel();
+    let timer = Timer::new(move || fired_tx.send(Instant::now()).is_ok());
+
+    let start = Instant::now();
+    timer.wake_at(start + Duration::from_millis(200));
+    // An earlier deadline that arrives later still fires first
+    timer.wake_at(start + Duration::from_millis(20));
+
+    let first = fired_rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert!(first >= start + Duration::from_millis(20));
+    assert!(first < start + Duration::from_millis(200));
+    let second = fired_rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert!(second >= start + Duration::from_millis(200));
+
+    // Deadlines that have passed fire right away
+    timer.wake_at(start);
+    assert!(fired_rx.recv_timeout(Duration::from_secs(5)).is_ok());
+}
+
+#[test]
+#[ignore = "opening an events loop needs a display"]
+fn test_event_stream_wakes_up() {
+    let mut core = Core::new().unwrap();
+    let event_stream = EventStream::new(GestureConfig::default(), &core.handle());
+    let notify = event_stream.notify_handle();
+    thread::spawn(move || {
+        thread::sleep(Duration::from_millis(20));
+        notify.notify(0);
+    });
+
+    // Waiting ends as soon as the proxy wakes up the stream
+    let (event, event_stream) = core.run(event_stream.into_future()).map_err(|_| ()).unwrap();
+    match event {
+        Some((None, WindowEvent::Wakeup)) => {},
+        event => panic!("expected a wakeup, got {:?}", event)
+    }
+
+    let deadline = Instant::now() + Duration::from_millis(20);
+    event_stream.wake_at(deadline);
+    let (event, _) = core.run(event_stream.into_future()).map_err(|_| ()).unwrap();
+    match event {
+        Some((None, WindowEvent::Timer)) => assert!(Instant::now() >= deadline),
+        event => panic!("expected a timer, got {:?}", event)
+    }
+}
+
+#[test]
+#[ignore = "opening an events loop needs a display"]
+fn test_event_stream_leaves_reactor_running() {
+    let mut core = Core::new().unwrap();
+    let handle = core.handle();
+    let event_stream = EventStream::new(GestureConfig::default(), &handle);
+
+    // A reactor timeout completes while the stream waits for window events
+    let start = Instant::now();
+    let timeout = Timeout::new(Duration::from_millis(20), &handle).unwrap();
+    match core.run(timeout.map_err(|_| ()).select2(event_stream.into_future().map_err(|_| ()))) {
+        Ok(Either::A(_)) => assert!(Instant::now() < start + Duration::from_secs(1)),
+        _ => panic!("expected the timeout to complete first")
+    }
+}